@@ -1,3 +1,16 @@
+use std::{collections::HashMap, io, sync::RwLock};
+
+use lazy_static::lazy_static;
+use log::{debug, warn};
+use unreal_modloader::unreal_asset::ue4version::{
+    VER_UE4_20, VER_UE4_21, VER_UE4_22, VER_UE4_23, VER_UE4_24, VER_UE4_25, VER_UE4_26, VER_UE4_27,
+};
+
+use crate::{error::IntegratorError, settings::SETTINGS};
+
+/// Engine version the embedded template assets were cooked with
+pub(crate) const TEMPLATE_ENGINE_VERSION: i32 = VER_UE4_23;
+
 pub(crate) const ACTOR_TEMPLATE_ASSET: &[u8] = include_bytes!("../assets/ActorTemplate.uasset");
 
 pub(crate) const ACTOR_TEMPLATE_EXPORT: &[u8] = include_bytes!("../assets/ActorTemplate.uexp");
@@ -7,3 +20,244 @@ pub(crate) const ALERT_MOD_NOTIFICATION_ACTOR_ASSET: &[u8] =
 
 pub(crate) const ALERT_MOD_NOTIFICATION_ACTOR_EXPORT: &[u8] =
     include_bytes!("../assets/alert_mod/NotificationActor.uexp");
+
+/// Engine versions mods can request for their own assets, by their `4.xx` name
+const SUPPORTED_ENGINE_VERSIONS: [(&str, i32); 8] = [
+    ("4.20", VER_UE4_20),
+    ("4.21", VER_UE4_21),
+    ("4.22", VER_UE4_22),
+    ("4.23", VER_UE4_23),
+    ("4.24", VER_UE4_24),
+    ("4.25", VER_UE4_25),
+    ("4.26", VER_UE4_26),
+    ("4.27", VER_UE4_27),
+];
+
+/// Resolve an engine version requested in a mod's metadata, like `4.23`
+pub(crate) fn parse_engine_version(version: &str) -> Result<i32, io::Error> {
+    SUPPORTED_ENGINE_VERSIONS
+        .iter()
+        .find(|(name, _)| *name == version)
+        .map(|(_, engine_version)| *engine_version)
+        .ok_or_else(|| {
+            IntegratorError::UnsupportedEngineVersion {
+                version: version.to_string(),
+                supported: SUPPORTED_ENGINE_VERSIONS
+                    .iter()
+                    .map(|e| e.0.to_string())
+                    .collect(),
+            }
+            .into()
+        })
+}
+
+/// Name of an engine version like `4.23`, `None` if mods can't request it
+pub(crate) fn engine_version_name(engine_version: i32) -> Option<&'static str> {
+    SUPPORTED_ENGINE_VERSIONS
+        .iter()
+        .find(|(_, e)| *e == engine_version)
+        .map(|(name, _)| *name)
+}
+
+/// Engine version game assets are read and written with when it can't be detected
+pub(crate) const DEFAULT_ENGINE_VERSION: i32 = VER_UE4_23;
+
+/// Tag every package file starts with
+const PACKAGE_FILE_TAG: u32 = 0x9E2A83C1;
+
+lazy_static! {
+    /// Engine version the settings name, `None` to detect it from the game's assets
+    static ref CONFIGURED_ENGINE_VERSION: Option<i32> =
+        SETTINGS.engine_version.as_ref().and_then(|version| {
+            match parse_engine_version(version) {
+                Ok(engine_version) => Some(engine_version),
+                Err(e) => {
+                    warn!(
+                        "Ignoring engine version {}, detecting it from the game's assets instead: {}",
+                        version, e
+                    );
+                    None
+                }
+            }
+        });
+    static ref DETECTED_ENGINE_VERSION: RwLock<Option<i32>> = RwLock::new(None);
+}
+
+/// Engine version of the game's assets, used for every asset the handlers read and write
+/// unless a mod requested another one.
+///
+/// The version from the settings, the one detected at the start of the integration,
+/// or [`DEFAULT_ENGINE_VERSION`] before anything was detected.
+pub fn game_engine_version() -> i32 {
+    CONFIGURED_ENGINE_VERSION
+        .or_else(|| {
+            *DETECTED_ENGINE_VERSION
+                .read()
+                .expect("Poisoned detected engine version")
+        })
+        .unwrap_or(DEFAULT_ENGINE_VERSION)
+}
+
+/// Object version a package's header was saved with, `None` for unversioned packages
+/// like most cooked game assets or data that isn't a UE4 package
+fn header_object_version(data: &[u8]) -> Option<i32> {
+    let read_i32 = |offset: usize| {
+        data.get(offset..offset + 4)
+            .map(|e| i32::from_le_bytes([e[0], e[1], e[2], e[3]]))
+    };
+
+    if read_i32(0)? as u32 != PACKAGE_FILE_TAG {
+        return None;
+    }
+    // -4 dropped the UE3 version, -8 and below are UE5 packages
+    let legacy_file_version = read_i32(4)?;
+    if !(-7..0).contains(&legacy_file_version) {
+        return None;
+    }
+    let object_version_offset = if legacy_file_version == -4 { 8 } else { 12 };
+
+    match read_i32(object_version_offset)? {
+        0 => None,
+        object_version => Some(object_version),
+    }
+}
+
+/// Newest engine version the integrator supports that assets of an object version can be from
+fn engine_version_for_object_version(object_version: i32) -> Option<i32> {
+    SUPPORTED_ENGINE_VERSIONS
+        .iter()
+        .map(|(_, engine_version)| *engine_version)
+        .filter(|e| *e <= object_version)
+        .max()
+}
+
+/// Detect the engine version of the game's assets from the header of one of them,
+/// unless the settings name one.
+///
+/// Only versioned packages record their engine version, for unversioned ones like most
+/// cooked builds nothing is detected. `header` is `None` if the asset couldn't be read,
+/// the game's version falls back to [`DEFAULT_ENGINE_VERSION`] when nothing is detected.
+pub(crate) fn detect_game_engine_version(asset_name: &str, header: Option<&[u8]>) {
+    if CONFIGURED_ENGINE_VERSION.is_some() {
+        return;
+    }
+
+    let detected = match header {
+        Some(header) => match header_object_version(header) {
+            Some(object_version) => {
+                let detected = engine_version_for_object_version(object_version);
+                if detected.is_none() {
+                    warn!(
+                        "{} was saved with unsupported object version {}, falling back to {}",
+                        asset_name,
+                        object_version,
+                        engine_version_name(DEFAULT_ENGINE_VERSION).unwrap_or_default()
+                    );
+                }
+                detected
+            }
+            // cooked game assets are usually unversioned, so this is the common case
+            None => {
+                debug!(
+                    "{} doesn't record an engine version, falling back to {}",
+                    asset_name,
+                    engine_version_name(DEFAULT_ENGINE_VERSION).unwrap_or_default()
+                );
+                None
+            }
+        },
+        None => {
+            warn!(
+                "Could not read {} to detect the engine version, falling back to {}",
+                asset_name,
+                engine_version_name(DEFAULT_ENGINE_VERSION).unwrap_or_default()
+            );
+            None
+        }
+    };
+
+    *DETECTED_ENGINE_VERSION
+        .write()
+        .expect("Poisoned detected engine version") = detected;
+}
+
+lazy_static! {
+    /// Engine versions mods requested for their own assets, by absolute asset path
+    static ref ASSET_ENGINE_VERSIONS: RwLock<HashMap<String, i32>> = RwLock::new(HashMap::new());
+}
+
+/// Replace the engine versions mods requested for their assets, for the next integration
+pub(crate) fn set_asset_engine_versions(versions: HashMap<String, i32>) {
+    *ASSET_ENGINE_VERSIONS
+        .write()
+        .expect("Poisoned asset engine versions") = versions;
+}
+
+/// Engine version to read and write an asset with, the one a mod requested or the game's
+pub(crate) fn asset_engine_version(asset_name: &str) -> i32 {
+    ASSET_ENGINE_VERSIONS
+        .read()
+        .expect("Poisoned asset engine versions")
+        .get(asset_name)
+        .copied()
+        .unwrap_or_else(game_engine_version)
+}
+
+/// Refuse to use the embedded templates for assets of a different engine version,
+/// parsing them at the wrong version would corrupt the integrated assets.
+pub(crate) fn check_template_engine_version(engine_version: i32) -> Result<(), io::Error> {
+    if engine_version != TEMPLATE_ENGINE_VERSION {
+        return Err(IntegratorError::TemplateEngineVersion {
+            template: TEMPLATE_ENGINE_VERSION,
+            game: engine_version,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use unreal_modloader::unreal_asset::ue4version::{VER_UE4_20, VER_UE4_23};
+
+    use super::{engine_version_for_object_version, header_object_version, PACKAGE_FILE_TAG};
+
+    fn header(fields: &[i32]) -> Vec<u8> {
+        let mut header = PACKAGE_FILE_TAG.to_le_bytes().to_vec();
+        for field in fields {
+            header.extend_from_slice(&field.to_le_bytes());
+        }
+        header
+    }
+
+    #[test]
+    fn versioned_headers_record_their_object_version() {
+        // legacy file version, UE3 version, UE4 version, licensee version
+        assert_eq!(
+            header_object_version(&header(&[-7, 864, VER_UE4_23, 0])),
+            Some(VER_UE4_23)
+        );
+        // -4 has no UE3 version
+        assert_eq!(
+            header_object_version(&header(&[-4, VER_UE4_23, 0])),
+            Some(VER_UE4_23)
+        );
+    }
+
+    #[test]
+    fn unversioned_and_foreign_headers_record_nothing() {
+        assert_eq!(header_object_version(&header(&[-7, 0, 0, 0])), None);
+        assert_eq!(header_object_version(&header(&[-8, 0, 1000, 0])), None);
+        assert_eq!(header_object_version(&[0; 16]), None);
+        assert_eq!(header_object_version(&header(&[-7])), None);
+    }
+
+    #[test]
+    fn object_versions_map_to_the_newest_engine_version_they_fit() {
+        assert_eq!(
+            engine_version_for_object_version(VER_UE4_23),
+            Some(VER_UE4_23)
+        );
+        assert_eq!(engine_version_for_object_version(VER_UE4_20 - 1), None);
+    }
+}
@@ -0,0 +1,125 @@
+use unreal_modloader::unreal_modintegrator::IntegratorConfig;
+
+use crate::AstroIntegratorConfig;
+
+/// Handler behavior changes, as the handler, the integrator version that changed it
+/// and what a mod made for an older version will see differently.
+/// Add an entry whenever a handler's output changes for the same metadata.
+const HANDLER_CHANGES: &[(&str, &str, &str)] = &[
+    (
+        "biome_placement_modifiers",
+        "0.1.5",
+        "modifiers are never applied to test maps, even with include_test_maps set",
+    ),
+    (
+        "linked_actor_components",
+        "0.1.5",
+        "component variable GUIDs are derived from the actor and component instead of being random",
+    ),
+    (
+        "item_list_entries",
+        "0.1.5",
+        "string entries are only turned into content paths for object arrays",
+    ),
+];
+
+/// Parse a `major.minor.patch` version into its numbers, `None` if it isn't one
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    version
+        .trim_start_matches('v')
+        .split('.')
+        .map(|e| e.parse::<u32>().ok())
+        .collect()
+}
+
+/// Warn about handlers a mod uses whose behavior changed since the integrator version it was made for.
+///
+/// `metadata` is the mod's whole metadata, mods that don't declare an `integrator_version`
+/// are assumed to be made for the running one.
+pub fn handler_compatibility_warnings(metadata: &serde_json::Value) -> Vec<String> {
+    let declared = match metadata.get("integrator_version").and_then(|e| e.as_str()) {
+        Some(declared) => declared,
+        None => return Vec::new(),
+    };
+    let declared_version = match parse_version(declared) {
+        Some(declared_version) => declared_version,
+        None => return vec![format!("Invalid integrator_version {:?}", declared)],
+    };
+
+    let mut warnings = Vec::new();
+    if parse_version(AstroIntegratorConfig::INTEGRATOR_VERSION)
+        .map(|e| declared_version > e)
+        .unwrap_or(false)
+    {
+        warnings.push(format!(
+            "Made for integrator {}, newer than the running {}",
+            declared,
+            AstroIntegratorConfig::INTEGRATOR_VERSION
+        ));
+    }
+
+    let integrator = match metadata.get("integrator").and_then(|e| e.as_object()) {
+        Some(integrator) => integrator,
+        None => return warnings,
+    };
+    for (handler_name, changed_in, change) in HANDLER_CHANGES {
+        if !integrator.contains_key(*handler_name) {
+            continue;
+        }
+        if parse_version(changed_in)
+            .map(|e| declared_version < e)
+            .unwrap_or(false)
+        {
+            warnings.push(format!(
+                "{} changed in integrator {}, after the {} this mod was made for: {}",
+                handler_name, changed_in, declared, change
+            ));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use unreal_modloader::unreal_modintegrator::IntegratorConfig;
+
+    use super::handler_compatibility_warnings;
+    use crate::AstroIntegratorConfig;
+
+    #[test]
+    fn only_changed_handlers_a_mod_uses_are_reported() {
+        let warnings = handler_compatibility_warnings(&json!({
+            "integrator_version": "0.1.4",
+            "integrator": {
+                "biome_placement_modifiers": [],
+                "string_table_entries": {},
+            },
+        }));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("biome_placement_modifiers changed in integrator 0.1.5"));
+    }
+
+    #[test]
+    fn mods_for_the_running_version_get_no_warnings() {
+        let integrator = json!({ "linked_actor_components": {}, "item_list_entries": {} });
+        assert!(handler_compatibility_warnings(&json!({
+            "integrator_version": AstroIntegratorConfig::INTEGRATOR_VERSION,
+            "integrator": integrator,
+        }))
+        .is_empty());
+        assert!(handler_compatibility_warnings(&json!({ "integrator": integrator })).is_empty());
+    }
+
+    #[test]
+    fn newer_and_invalid_versions_are_reported() {
+        let warnings = handler_compatibility_warnings(&json!({ "integrator_version": "v99.0.0" }));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("Made for integrator v99.0.0, newer than the running"));
+
+        assert_eq!(
+            handler_compatibility_warnings(&json!({ "integrator_version": "latest" })),
+            [String::from("Invalid integrator_version \"latest\"")]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde_json::json;
+use unreal_modloader::unreal_asset::{
+    exports::{ExportBaseTrait, ExportNormalTrait},
+    properties::PropertyDataTrait,
+    unreal_types::PackageIndex,
+    Asset,
+};
+use unreal_modloader::unreal_modintegrator::{
+    helpers::{find_asset, game_to_absolute, read_asset},
+    IntegratorConfig,
+};
+use unreal_modloader::unreal_pak::PakFile;
+
+use crate::{
+    assets::{asset_engine_version, game_engine_version},
+    error::IntegratorError,
+    handlers::normalize_content_path,
+    AstroIntegratorConfig,
+};
+
+/// Paks an asset is looked up in by its content path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetSource {
+    /// The paks the game ships with, the asset as the game has it
+    Game,
+    /// The pak the integrator wrote, the asset with every handler's edits
+    Integrated,
+}
+
+impl FromStr for AssetSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "game" => Ok(AssetSource::Game),
+            "integrated" => Ok(AssetSource::Integrated),
+            _ => Err(format!(
+                "Unknown asset source {}, expected game or integrated",
+                s
+            )),
+        }
+    }
+}
+
+/// Read a loose asset, like one extracted from a pak, together with its `.uexp`
+pub fn read_loose_asset(path: &Path) -> Result<Asset, io::Error> {
+    let asset_data = fs::read(path)?;
+    let export_path = path.with_extension("uexp");
+    let export_data = match export_path.is_file() {
+        true => Some(fs::read(&export_path)?),
+        false => None,
+    };
+
+    let mut asset = Asset::new(asset_data, export_data);
+    asset.engine_version = game_engine_version();
+    asset
+        .parse_data()
+        .map_err(|e| IntegratorError::asset_data(&path.to_string_lossy(), e))?;
+    Ok(asset)
+}
+
+/// Open paks and read their index, entry data is only read once an asset is looked up
+pub fn open_paks(paths: &[PathBuf]) -> Result<Vec<PakFile>, io::Error> {
+    let mut paks = Vec::new();
+    for path in paths {
+        let mut pak = PakFile::reader(BufReader::new(File::open(path)?));
+        pak.load_version()
+            .map_err(|e| IntegratorError::pak_io(&path.to_string_lossy(), e))?;
+        pak.load_records()
+            .map_err(|e| IntegratorError::pak_io(&path.to_string_lossy(), e))?;
+        paks.push(pak);
+    }
+    Ok(paks)
+}
+
+/// Read an asset by its content path, like `/Game/Items/ItemList.uasset`,
+/// from the first of the paks holding it
+#[allow(clippy::ptr_arg)]
+pub fn read_pak_asset(paks: &mut Vec<PakFile>, content_path: &str) -> Result<Asset, io::Error> {
+    let asset_name = game_to_absolute(
+        AstroIntegratorConfig::GAME_NAME,
+        &normalize_content_path(content_path),
+    )
+    .ok_or_else(|| IntegratorError::invalid_path(content_path))?;
+
+    let index = find_asset(paks, &asset_name).ok_or_else(|| IntegratorError::AssetNotFound {
+        path: asset_name.clone(),
+        referenced_by: String::from("Dumped asset"),
+        source: io::Error::from(io::ErrorKind::NotFound),
+    })?;
+    read_asset(
+        &mut paks[index],
+        asset_engine_version(&asset_name),
+        &asset_name,
+    )
+    .map_err(|e| IntegratorError::pak_io(&asset_name, e).into())
+}
+
+/// Describe what a package index points to
+fn describe_index(asset: &Asset, index: PackageIndex) -> serde_json::Value {
+    if index.index == 0 {
+        return serde_json::Value::Null;
+    }
+
+    if index.is_import() {
+        let name = asset
+            .imports
+            .get((-index.index - 1) as usize)
+            .map(|e| e.object_name.content.clone());
+        json!({ "import": -index.index - 1, "name": name })
+    } else {
+        let name = asset
+            .exports
+            .get((index.index - 1) as usize)
+            .map(|e| e.get_base_export().object_name.content.clone());
+        json!({ "export": index.index - 1, "name": name })
+    }
+}
+
+/// Dump the imports, exports and properties of an asset to json
+/// so mod authors can see the structure they are trying to edit
+pub fn dump_asset(asset: &Asset) -> serde_json::Value {
+    let imports: Vec<serde_json::Value> = asset
+        .imports
+        .iter()
+        .map(|e| {
+            json!({
+                "class_package": e.class_package.content,
+                "class_name": e.class_name.content,
+                "object_name": e.object_name.content,
+                "outer": describe_index(asset, e.outer_index),
+            })
+        })
+        .collect();
+
+    let exports: Vec<serde_json::Value> = asset
+        .exports
+        .iter()
+        .map(|export| {
+            let base_export = export.get_base_export();
+            let properties: Vec<serde_json::Value> = export
+                .get_normal_export()
+                .map(|e| {
+                    e.properties
+                        .iter()
+                        .map(|e| {
+                            json!({
+                                "name": e.get_name().content,
+                                "value": format!("{:?}", e),
+                            })
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            json!({
+                "object_name": base_export.object_name.content,
+                "class": describe_index(asset, base_export.class_index),
+                "outer": describe_index(asset, base_export.outer_index),
+                "template": describe_index(asset, base_export.template_index),
+                "properties": properties,
+            })
+        })
+        .collect();
+
+    json!({
+        "engine_version": asset.engine_version,
+        "imports": imports,
+        "exports": exports,
+    })
+}
+
+/// List the entries of a pak with their uncompressed sizes, sorted by path.
+///
+/// Only the pak index is read, entry data is left alone.
+pub fn list_pak_entries(path: &Path) -> Result<Vec<(String, u64)>, io::Error> {
+    let file = File::open(path)?;
+    let mut pak = PakFile::reader(BufReader::new(&file));
+    pak.load_version()
+        .map_err(|e| IntegratorError::pak_io(&path.to_string_lossy(), e))?;
+    pak.load_records()
+        .map_err(|e| IntegratorError::pak_io(&path.to_string_lossy(), e))?;
+
+    let mut entries: Vec<(String, u64)> = pak
+        .get_entry_names()
+        .into_iter()
+        .filter_map(|name| {
+            pak.get_record(name)
+                .map(|record| (name.clone(), record.decompressed_size))
+        })
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
@@ -0,0 +1,221 @@
+use std::{
+    error::Error,
+    fmt::{self, Display},
+    io::{self, ErrorKind},
+};
+
+use unreal_modloader::unreal_asset::properties::Property;
+
+/// Why a handler failed, naming the handler, asset or metadata field responsible.
+///
+/// The integrator is generic over the error type of the handlers, this crate picks
+/// [`io::Error`] in its [`IntegratorConfig`](unreal_modloader::unreal_modintegrator::IntegratorConfig)
+/// so these are wrapped in one, use [`IntegratorError::from_io_error`] to get them back.
+#[derive(Debug)]
+pub enum IntegratorError {
+    /// A mod's metadata for a handler doesn't have the expected shape
+    InvalidMetadata { handler: String, field: String },
+    /// Mods set the same metadata field to values that can't be merged
+    ConflictingMetadata { handler: String, field: String },
+    /// A value from a mod's metadata doesn't fit the property it goes into
+    InvalidValue { property: String, reason: String },
+    /// A path from a mod's metadata isn't a valid game or item path
+    InvalidPath { path: String },
+    /// An asset a mod references exists neither in the game nor in any mod
+    AssetNotFound {
+        path: String,
+        referenced_by: String,
+        source: io::Error,
+    },
+    /// An asset lacks the export, array, row or field a handler edits
+    MissingExport { asset: String, export: String },
+    /// A property is of a type the handlers can't write
+    UnsupportedProperty {
+        property: String,
+        property_type: String,
+    },
+    /// An asset doesn't have the structure its class guarantees
+    CorruptedAsset { asset: String, reason: String },
+    /// Parsing or serializing an asset failed
+    AssetData {
+        asset: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// The outer chain of an added import doesn't end at a package
+    BrokenImportChain { chain: Vec<String> },
+    /// The configured engine version isn't one the integrator can read
+    UnsupportedEngineVersion {
+        version: String,
+        supported: Vec<String>,
+    },
+    /// The embedded templates were cooked for another engine version than the game's
+    TemplateEngineVersion { template: i32, game: i32 },
+    /// The game is running and could read a half written pak
+    GameRunning { executable: String },
+    /// A mod's metadata doesn't match the metadata schema and strict metadata is enabled
+    SchemaViolation {
+        handler: String,
+        problems: Vec<String>,
+    },
+    /// Enabled mods target other integrator or engine versions and strict metadata is enabled
+    VersionMismatch { problems: Vec<String> },
+    /// Reading from or writing to a pak failed
+    PakIo {
+        asset: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+}
+
+impl IntegratorError {
+    pub fn invalid_metadata(handler: &str, field: &str) -> Self {
+        IntegratorError::InvalidMetadata {
+            handler: handler.to_string(),
+            field: field.to_string(),
+        }
+    }
+
+    pub fn invalid_value(property: &str, reason: &str) -> Self {
+        IntegratorError::InvalidValue {
+            property: property.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn invalid_path(path: &str) -> Self {
+        IntegratorError::InvalidPath {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn missing_export(asset: &str, export: &str) -> Self {
+        IntegratorError::MissingExport {
+            asset: asset.to_string(),
+            export: export.to_string(),
+        }
+    }
+
+    pub fn unsupported_property(property: &Property, name: &str) -> Self {
+        IntegratorError::UnsupportedProperty {
+            property: name.to_string(),
+            property_type: property_type_name(property),
+        }
+    }
+
+    pub fn corrupted_asset(asset: &str, reason: &str) -> Self {
+        IntegratorError::CorruptedAsset {
+            asset: asset.to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    pub fn asset_data(asset: &str, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        IntegratorError::AssetData {
+            asset: asset.to_string(),
+            source: source.into(),
+        }
+    }
+
+    pub fn pak_io(asset: &str, source: impl Into<Box<dyn Error + Send + Sync>>) -> Self {
+        IntegratorError::PakIo {
+            asset: asset.to_string(),
+            source: source.into(),
+        }
+    }
+
+    /// The integrator error an [`io::Error`] returned by a handler wraps, if any
+    pub fn from_io_error(error: &io::Error) -> Option<&IntegratorError> {
+        error.get_ref().and_then(|e| e.downcast_ref())
+    }
+}
+
+/// Name of a property's type like `StructProperty`, for errors about properties
+/// the handlers can't write
+pub fn property_type_name(property: &Property) -> String {
+    // the variants are named after the property types
+    format!("{:?}", property)
+        .split(|e: char| !e.is_alphanumeric())
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+impl Display for IntegratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IntegratorError::InvalidMetadata { handler, field } => {
+                write!(f, "Invalid {} metadata at {}", handler, field)
+            }
+            IntegratorError::ConflictingMetadata { handler, field } => {
+                write!(f, "Mods set different {} for {}", field, handler)
+            }
+            IntegratorError::InvalidValue { property, reason } => {
+                write!(f, "Invalid value for {}: {}", property, reason)
+            }
+            IntegratorError::InvalidPath { path } => write!(f, "Invalid path {}", path),
+            IntegratorError::AssetNotFound {
+                path,
+                referenced_by,
+                source,
+            } => write!(f, "{} {} not found: {}", referenced_by, path, source),
+            IntegratorError::MissingExport { asset, export } => {
+                write!(f, "{} has no {}", asset, export)
+            }
+            IntegratorError::UnsupportedProperty {
+                property,
+                property_type,
+            } => write!(f, "{} is a {}, which can't be written", property, property_type),
+            IntegratorError::CorruptedAsset { asset, reason } => {
+                write!(f, "Corrupted asset {}: {}", asset, reason)
+            }
+            IntegratorError::AssetData { asset, source } => {
+                write!(f, "Failed to parse or serialize {}: {}", asset, source)
+            }
+            IntegratorError::BrokenImportChain { chain } => write!(
+                f,
+                "Broken import chain, expected it to end at a package: {}",
+                chain.join(" -> ")
+            ),
+            IntegratorError::UnsupportedEngineVersion { version, supported } => write!(
+                f,
+                "Unsupported engine version {}, supported versions are {}",
+                version,
+                supported.join(", ")
+            ),
+            IntegratorError::TemplateEngineVersion { template, game } => write!(
+                f,
+                "Embedded templates were cooked for engine version {} but the game uses {}, this integrator version does not support the game",
+                template, game
+            ),
+            IntegratorError::GameRunning { executable } => write!(
+                f,
+                "Astroneer is running ({}), close the game before integrating mods",
+                executable
+            ),
+            IntegratorError::SchemaViolation { handler, problems } => {
+                write!(f, "Invalid {} metadata: {}", handler, problems.join("; "))
+            }
+            IntegratorError::VersionMismatch { problems } => write!(f, "{}", problems.join("; ")),
+            IntegratorError::PakIo { asset, source } => {
+                write!(f, "Failed to access {} in the paks: {}", asset, source)
+            }
+        }
+    }
+}
+
+impl Error for IntegratorError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            IntegratorError::AssetNotFound { source, .. } => Some(source),
+            IntegratorError::AssetData { source, .. } | IntegratorError::PakIo { source, .. } => {
+                Some(source.as_ref())
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<IntegratorError> for io::Error {
+    fn from(e: IntegratorError) -> Self {
+        io::Error::new(ErrorKind::Other, e)
+    }
+}
@@ -1,23 +1,27 @@
-use log::warn;
+use log::{info, warn};
 use serde::{Deserialize, Serialize};
-use std::{
-    collections::HashMap,
-    io::{self, ErrorKind},
-    path::Path,
-};
+use std::{collections::HashMap, io, path::Path};
 use unreal_modloader::unreal_asset::{
     cast,
     exports::ExportNormalTrait,
     properties::{object_property::ObjectProperty, Property, PropertyDataTrait},
     reader::asset_trait::AssetTrait,
-    ue4version::VER_UE4_23,
     unreal_types::{FName, PackageIndex},
     Import,
 };
-use unreal_modloader::unreal_modintegrator::{helpers::get_asset, write_asset};
+use unreal_modloader::unreal_modintegrator::helpers::get_asset;
 use unreal_modloader::unreal_pak::PakFile;
 
-use super::MAP_PATHS;
+use crate::assets::asset_engine_version;
+use crate::error::IntegratorError;
+use crate::schema::validate_metadata;
+
+use super::report::{Change, HandlerReport};
+use super::timing::{AssetPhase, AssetTimings};
+use super::{
+    handler_map_paths, is_excluded_map, is_test_map, normalize_content_path, verify_import_chain,
+    write_integrated_asset,
+};
 
 #[derive(Deserialize, Serialize, Debug)]
 enum BiomeType {
@@ -32,6 +36,9 @@ struct PlacementModifier {
     pub biome_name: String,
     pub layer_name: String,
     pub placements: Vec<String>,
+    /// Maps this modifier must not be added to
+    #[serde(default)]
+    pub exclude_maps: Vec<String>,
 }
 
 #[allow(clippy::ptr_arg)]
@@ -42,26 +49,51 @@ pub(crate) fn handle_biome_placement_modifiers(
     mod_paks: &mut Vec<PakFile>,
     placement_modifiers: &Vec<serde_json::Value>,
 ) -> Result<(), io::Error> {
+    validate_metadata("biome_placement_modifiers", placement_modifiers)?;
+
     let mut biome_placement_modifiers = Vec::new();
 
-    for modifiers in placement_modifiers {
-        let modifiers: Vec<PlacementModifier> = serde_json::from_value(modifiers.clone())
-            .map_err(|e| io::Error::new(ErrorKind::Other, e))?;
+    for (mod_index, modifiers) in placement_modifiers.iter().enumerate() {
+        let modifiers: Vec<PlacementModifier> = match serde_json::from_value(modifiers.clone()) {
+            Ok(modifiers) => modifiers,
+            Err(e) => {
+                warn!(
+                    "Skipping biome_placement_modifiers of mod #{}: {}",
+                    mod_index + 1,
+                    e
+                );
+                continue;
+            }
+        };
 
-        biome_placement_modifiers.extend(modifiers);
+        biome_placement_modifiers.extend(modifiers.into_iter().map(|mut e| {
+            e.placements = e
+                .placements
+                .iter()
+                .map(|e| normalize_content_path(e))
+                .collect();
+            (mod_index, e)
+        }));
     }
 
-    for map_path in MAP_PATHS {
-        if map_path == "Astro/Content/Maps/test/BasicSphereT2.umap" {
+    let mut timings = AssetTimings::new("biome_placement_modifiers");
+    let mut report = HandlerReport::new("biome_placement_modifiers");
+
+    for map_path in handler_map_paths("biome_placement_modifiers") {
+        // test maps don't have the planets' voxel volumes
+        if is_test_map(map_path) {
             continue;
         }
+        let timer = timings.start();
         let mut asset = get_asset(
             integrated_pak,
             game_paks,
             mod_paks,
             &map_path.to_string(),
-            VER_UE4_23,
+            asset_engine_version(map_path),
         )?;
+        timings.record(map_path, AssetPhase::Read, timer);
+        let timer = timings.start();
 
         let mut voxel_exports = HashMap::new();
 
@@ -71,7 +103,7 @@ pub(crate) fn handle_biome_placement_modifiers(
                 let class_index = normal_export.base_export.class_index;
                 if class_index.is_import() {
                     let import = asset.get_import(class_index).ok_or_else(|| {
-                        io::Error::new(ErrorKind::Other, "Corrupted game installation")
+                        IntegratorError::corrupted_asset(map_path, "export class import not found")
                     })?;
 
                     if import.object_name.content == "VoxelVolumeComponent"
@@ -84,18 +116,21 @@ pub(crate) fn handle_biome_placement_modifiers(
             }
         }
 
-        for modifier in &biome_placement_modifiers {
+        for (mod_index, modifier) in &biome_placement_modifiers {
+            if is_excluded_map(map_path, &modifier.exclude_maps) {
+                info!(
+                    "Not adding placements to {} {} in excluded map {}",
+                    modifier.biome_name, modifier.layer_name, map_path
+                );
+                continue;
+            }
+
             let mut modifier_imports = Vec::new();
             for placement_path in &modifier.placements {
                 let placement_name = Path::new(placement_path)
                     .file_stem()
                     .and_then(|e| e.to_str())
-                    .ok_or_else(|| {
-                        io::Error::new(
-                            ErrorKind::Other,
-                            format!("Invalid placement {}", placement_path),
-                        )
-                    })?;
+                    .ok_or_else(|| IntegratorError::invalid_path(placement_path))?;
 
                 asset.add_fname("/Script/CoreUObject");
                 asset.add_fname("Package");
@@ -119,6 +154,7 @@ pub(crate) fn handle_biome_placement_modifiers(
                     object_name: FName::from_slice(placement_name),
                 };
                 let modifier_import = asset.add_import(modifier_import);
+                verify_import_chain(&asset, modifier_import)?;
                 modifier_imports.push(modifier_import);
             }
 
@@ -129,6 +165,7 @@ pub(crate) fn handle_biome_placement_modifiers(
                     "Failed to find voxel export {} for {}",
                     voxels_name, map_path
                 );
+                report.skipped(*mod_index);
                 continue;
             }
 
@@ -152,9 +189,9 @@ pub(crate) fn handle_biome_placement_modifiers(
             }
 
             let biome_property_index = biome_property_index.ok_or_else(|| {
-                io::Error::new(
-                    ErrorKind::Other,
-                    format!("Failed to find biome type {}", biome_property_name),
+                IntegratorError::missing_export(
+                    map_path,
+                    &format!("{} in {}", biome_property_name, voxels_name),
                 )
             })?;
             let biome_property = cast!(
@@ -162,7 +199,12 @@ pub(crate) fn handle_biome_placement_modifiers(
                 ArrayProperty,
                 &mut export.properties[biome_property_index]
             )
-            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Corrupted game installation"))?;
+            .ok_or_else(|| {
+                IntegratorError::corrupted_asset(
+                    map_path,
+                    &format!("{} isn't an array", biome_property_name),
+                )
+            })?;
 
             let biome = biome_property
                 .value
@@ -175,9 +217,9 @@ pub(crate) fn handle_biome_placement_modifiers(
                         .any(|e| e.value.content == modifier.biome_name)
                 })
                 .ok_or_else(|| {
-                    io::Error::new(
-                        ErrorKind::Other,
-                        format!("Failed to find biome {}", modifier.biome_name),
+                    IntegratorError::missing_export(
+                        map_path,
+                        &format!("biome {} in {}", modifier.biome_name, voxels_name),
                     )
                 })?;
 
@@ -186,7 +228,12 @@ pub(crate) fn handle_biome_placement_modifiers(
                 .iter_mut()
                 .find(|e| e.get_name().content == "Layers")
                 .and_then(|e| cast!(Property, ArrayProperty, e))
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Corrupted game installation"))?;
+                .ok_or_else(|| {
+                    IntegratorError::corrupted_asset(
+                        map_path,
+                        &format!("biome {} has no Layers array", modifier.biome_name),
+                    )
+                })?;
 
             let layer = layers
                 .value
@@ -199,10 +246,10 @@ pub(crate) fn handle_biome_placement_modifiers(
                         .any(|e| e.value.content == modifier.layer_name)
                 })
                 .ok_or_else(|| {
-                    io::Error::new(
-                        ErrorKind::Other,
-                        format!(
-                            "Failed to find layer {} for biome {}",
+                    IntegratorError::missing_export(
+                        map_path,
+                        &format!(
+                            "layer {} of biome {}",
                             modifier.layer_name, modifier.biome_name
                         ),
                     )
@@ -214,7 +261,13 @@ pub(crate) fn handle_biome_placement_modifiers(
                 .find(|e| e.get_name().content == "ObjectPlacementModifiers")
                 .and_then(|e| cast!(Property, ArrayProperty, e))
                 .ok_or_else(|| {
-                    io::Error::new(ErrorKind::Other, "Corrupted game installation".to_string())
+                    IntegratorError::corrupted_asset(
+                        map_path,
+                        &format!(
+                            "layer {} has no ObjectPlacementModifiers array",
+                            modifier.layer_name
+                        ),
+                    )
                 })?;
 
             for import_index in &modifier_imports {
@@ -228,11 +281,27 @@ pub(crate) fn handle_biome_placement_modifiers(
                     .value
                     .push(placement_modifier.into());
             }
+            report.applied(
+                *mod_index,
+                map_path,
+                Change::Added,
+                &format!(
+                    "{} placements to {} {}",
+                    modifier.placements.len(),
+                    modifier.biome_name,
+                    modifier.layer_name
+                ),
+            );
         }
 
-        write_asset(integrated_pak, &asset, &map_path.to_string())
-            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+        timings.record(map_path, AssetPhase::Modify, timer);
+
+        let timer = timings.start();
+        write_integrated_asset(integrated_pak, &asset, &map_path.to_string())?;
+        timings.record(map_path, AssetPhase::Write, timer);
     }
 
+    timings.finish();
+    report.log();
     Ok(())
 }
@@ -0,0 +1,188 @@
+use std::{collections::HashMap, io};
+
+use log::info;
+use unreal_modloader::unreal_asset::{
+    cast,
+    exports::{data_table_export::DataTableExport, Export},
+    properties::PropertyDataTrait,
+    Asset,
+};
+use unreal_modloader::unreal_modintegrator::{
+    helpers::{game_to_absolute, get_asset},
+    IntegratorConfig,
+};
+use unreal_modloader::unreal_pak::PakFile;
+
+use super::item_list_entries::set_struct_field;
+use super::report::{Change, HandlerReport};
+use super::timing::{AssetPhase, AssetTimings};
+use super::{expect_object, normalize_content_path, write_integrated_asset};
+use crate::{
+    assets::asset_engine_version, error::IntegratorError, schema::validate_metadata,
+    AstroIntegratorConfig,
+};
+
+/// A row to add to a DataTable, or to merge into the row of the same name
+#[derive(Debug)]
+struct DataTableRow {
+    mod_index: usize,
+    row_name: String,
+    columns: serde_json::Map<String, serde_json::Value>,
+}
+
+fn find_data_table(asset: &Asset) -> Option<usize> {
+    asset
+        .exports
+        .iter()
+        .position(|e| cast!(Export, DataTableExport, e).is_some())
+}
+
+/// Add a row to a DataTable or change the given columns of an existing row.
+///
+/// New rows start as a copy of the table's first row, so columns that aren't given
+/// keep the first row's values. Returns whether the row was added or merged into an existing one.
+fn apply_row(
+    asset: &mut Asset,
+    asset_name: &str,
+    export_index: usize,
+    row: &DataTableRow,
+) -> Result<Change, io::Error> {
+    let data_table =
+        cast!(Export, DataTableExport, &asset.exports[export_index]).expect("Corrupted memory");
+    let existing_row = data_table
+        .table
+        .data
+        .iter()
+        .position(|e| e.name.content == row.row_name);
+
+    let mut table_row = match existing_row {
+        Some(existing_row) => {
+            info!(
+                "Merging into existing row {} of {}",
+                row.row_name, asset_name
+            );
+            data_table.table.data[existing_row].clone()
+        }
+        None => {
+            let mut table_row = data_table.table.data.first().cloned().ok_or_else(|| {
+                IntegratorError::missing_export(
+                    asset_name,
+                    &format!("row to copy the row struct of {} from", row.row_name),
+                )
+            })?;
+            table_row.name = asset.add_fname(&row.row_name);
+            table_row
+        }
+    };
+
+    for (column_name, value) in &row.columns {
+        let property = table_row
+            .value
+            .iter_mut()
+            .find(|e| &e.get_name().content == column_name)
+            .ok_or_else(|| {
+                IntegratorError::missing_export(
+                    asset_name,
+                    &format!("column {} for row {}", column_name, row.row_name),
+                )
+            })?;
+        set_struct_field(asset, property, value)?;
+    }
+
+    let data_table =
+        cast!(Export, DataTableExport, &mut asset.exports[export_index]).expect("Corrupted memory");
+    match existing_row {
+        Some(existing_row) => {
+            data_table.table.data[existing_row] = table_row;
+            Ok(Change::Merged)
+        }
+        None => {
+            data_table.table.data.push(table_row);
+            Ok(Change::Added)
+        }
+    }
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_data_table_entries(
+    _data: &(),
+    integrated_pak: &mut PakFile,
+    game_paks: &mut Vec<PakFile>,
+    mod_paks: &mut Vec<PakFile>,
+    data_table_entries_maps: &Vec<serde_json::Value>,
+) -> Result<(), io::Error> {
+    validate_metadata("data_table_entries", data_table_entries_maps)?;
+
+    let mut new_rows: HashMap<String, Vec<DataTableRow>> = HashMap::new();
+    for (mod_index, data_table_entries_map) in data_table_entries_maps.iter().enumerate() {
+        let data_table_entries_map =
+            match expect_object("data_table_entries", mod_index, data_table_entries_map) {
+                Some(data_table_entries_map) => data_table_entries_map,
+                None => continue,
+            };
+
+        for (asset_name, rows) in data_table_entries_map {
+            let rows = rows.as_object().ok_or_else(|| {
+                IntegratorError::invalid_metadata("data_table_entries", asset_name)
+            })?;
+            let asset_rows = new_rows.entry(asset_name.clone()).or_default();
+
+            for (row_name, columns) in rows {
+                let columns = columns.as_object().ok_or_else(|| {
+                    IntegratorError::invalid_metadata(
+                        "data_table_entries",
+                        &format!("{}.{}", asset_name, row_name),
+                    )
+                })?;
+                asset_rows.push(DataTableRow {
+                    mod_index,
+                    row_name: row_name.clone(),
+                    columns: columns.clone(),
+                });
+            }
+        }
+    }
+
+    let mut timings = AssetTimings::new("data_table_entries");
+    let mut report = HandlerReport::new("data_table_entries");
+    for (asset_name, rows) in &new_rows {
+        let asset_name = game_to_absolute(
+            AstroIntegratorConfig::GAME_NAME,
+            &normalize_content_path(asset_name),
+        )
+        .ok_or_else(|| IntegratorError::invalid_metadata("data_table_entries", asset_name))?;
+        let timer = timings.start();
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            asset_engine_version(&asset_name),
+        )?;
+        timings.record(&asset_name, AssetPhase::Read, timer);
+        let timer = timings.start();
+
+        let export_index = find_data_table(&asset)
+            .ok_or_else(|| IntegratorError::missing_export(&asset_name, "DataTable export"))?;
+
+        for row in rows {
+            let change = apply_row(&mut asset, &asset_name, export_index, row)?;
+            report.applied(
+                row.mod_index,
+                &asset_name,
+                change,
+                &format!("row {}", row.row_name),
+            );
+        }
+
+        timings.record(&asset_name, AssetPhase::Modify, timer);
+
+        let timer = timings.start();
+        write_integrated_asset(integrated_pak, &asset, &asset_name)?;
+        timings.record(&asset_name, AssetPhase::Write, timer);
+    }
+
+    timings.finish();
+    report.log();
+    Ok(())
+}
@@ -1,28 +1,282 @@
-use std::{
-    collections::HashMap,
-    io::{self, ErrorKind},
-    path::Path,
-};
+use log::{debug, warn};
+use std::{collections::HashMap, io, path::Path};
 
 use unreal_modloader::unreal_asset::{
     cast,
     exports::{Export, ExportNormalTrait},
     properties::{
+        int_property::{BoolProperty, FloatProperty, IntProperty},
         object_property::{ObjectProperty, SoftObjectProperty},
-        Property,
+        str_property::{NameProperty, StrProperty},
+        struct_property::StructProperty,
+        Property, PropertyDataTrait,
     },
     reader::asset_trait::AssetTrait,
-    ue4version::VER_UE4_23,
     unreal_types::{FName, PackageIndex},
-    Import,
+    Asset, Import,
 };
 use unreal_modloader::unreal_modintegrator::{
     helpers::{game_to_absolute, get_asset},
-    write_asset, IntegratorConfig,
+    IntegratorConfig,
 };
 use unreal_modloader::unreal_pak::PakFile;
 
-use crate::AstroIntegratorConfig;
+use super::report::{Change, HandlerReport};
+use super::timing::{AssetPhase, AssetTimings};
+use super::{
+    expect_object, normalize_content_path, verify_import_chain, verify_soft_object_names,
+    write_integrated_asset,
+};
+use crate::{
+    assets::asset_engine_version,
+    error::{property_type_name, IntegratorError},
+    schema::validate_metadata,
+    AstroIntegratorConfig,
+};
+
+/// A single entry to append to an item list array
+#[derive(Debug)]
+enum ItemListEntry {
+    /// Path to an item for object and soft object arrays,
+    /// or the value itself for string and name arrays
+    Path(String),
+    /// Sub-property values of a struct element, used for struct arrays
+    Struct(serde_json::Map<String, serde_json::Value>),
+}
+
+/// Split an item path into the package name, class name and soft class name
+pub(crate) fn split_item_path(item_path: &str) -> Result<(String, String, String), io::Error> {
+    match item_path.contains('.') {
+        true => {
+            let split: Vec<&str> = item_path.split('.').collect();
+            Ok((
+                split[0].to_string(),
+                split[1].to_string(),
+                split[1].to_string(),
+            ))
+        }
+        false => Ok((
+            item_path.to_string(),
+            Path::new(item_path)
+                .file_stem()
+                .and_then(|e| e.to_str())
+                .map(|e| String::from(e) + "_C")
+                .ok_or_else(|| IntegratorError::invalid_path(item_path))?,
+            Path::new(item_path)
+                .file_stem()
+                .and_then(|e| e.to_str())
+                .map(|e| e.to_string())
+                .ok_or_else(|| IntegratorError::invalid_path(item_path))?,
+        )),
+    }
+}
+
+/// Add the package and blueprint generated class imports for an item
+pub(crate) fn add_class_import(
+    asset: &mut Asset,
+    real_name: &str,
+    class_name: &str,
+) -> Result<PackageIndex, io::Error> {
+    asset.add_name_reference(real_name.to_string(), false);
+    asset.add_name_reference(class_name.to_string(), false);
+
+    let inner_import = Import {
+        class_package: FName::from_slice("/Script/CoreUObject"),
+        class_name: FName::from_slice("Package"),
+        outer_index: PackageIndex::new(0),
+        object_name: FName::new(real_name.to_string(), 0),
+    };
+    let inner_import = asset.add_import(inner_import);
+
+    let import = Import {
+        class_package: FName::from_slice("/Script/Engine"),
+        class_name: FName::from_slice("BlueprintGeneratedClass"),
+        outer_index: inner_import,
+        object_name: FName::new(class_name.to_string(), 0),
+    };
+    let import = asset.add_import(import);
+    verify_import_chain(asset, import)?;
+    Ok(import)
+}
+
+/// Set a struct sub-property from its json value.
+///
+/// Nested structs take an object of the fields to change, enums their full value name
+/// like `EItemType::Resource`.
+pub(crate) fn set_struct_field(
+    asset: &mut Asset,
+    property: &mut Property,
+    value: &serde_json::Value,
+) -> Result<(), io::Error> {
+    let field_name = property.get_name().content.clone();
+    let property_type = property_type_name(property);
+    let invalid_value = || {
+        IntegratorError::invalid_value(
+            &field_name,
+            &format!("{} doesn't fit a {}", value, property_type),
+        )
+    };
+
+    if let Some(object_property) = cast!(Property, ObjectProperty, property) {
+        let item_path = value.as_str().ok_or_else(invalid_value)?;
+        let (real_name, class_name, _) = split_item_path(&normalize_content_path(item_path))?;
+        object_property.value = add_class_import(asset, &real_name, &class_name)?;
+    } else if let Some(soft_object_property) = cast!(Property, SoftObjectProperty, property) {
+        let item_path = value.as_str().ok_or_else(invalid_value)?;
+        let (real_name, _, soft_class_name) = split_item_path(&normalize_content_path(item_path))?;
+        asset.add_name_reference(real_name.clone(), false);
+        asset.add_name_reference(real_name.clone() + "." + &soft_class_name, false);
+        soft_object_property.value = FName::new(real_name + "." + &soft_class_name, 0);
+        soft_object_property.id = 0;
+    } else if let Some(name_property) = cast!(Property, NameProperty, property) {
+        let name = value.as_str().ok_or_else(invalid_value)?;
+        name_property.value = asset.add_fname(name);
+    } else if let Some(int_property) = cast!(Property, IntProperty, property) {
+        int_property.value = value
+            .as_i64()
+            .and_then(|e| i32::try_from(e).ok())
+            .ok_or_else(invalid_value)?;
+    } else if let Some(float_property) = cast!(Property, FloatProperty, property) {
+        float_property.value = (value.as_f64().ok_or_else(invalid_value)? as f32).into();
+    } else if let Some(bool_property) = cast!(Property, BoolProperty, property) {
+        bool_property.value = value.as_bool().ok_or_else(invalid_value)?;
+    } else if let Some(str_property) = cast!(Property, StrProperty, property) {
+        str_property.value = Some(value.as_str().ok_or_else(invalid_value)?.to_string());
+    } else if let Some(text_property) = cast!(Property, TextProperty, property) {
+        // texts from a string table only hold a key into it, their string lives in the table
+        if text_property.table_id.is_some() {
+            return Err(IntegratorError::invalid_value(
+                &field_name,
+                "the text comes from a string table, change it with string_table_entries",
+            )
+            .into());
+        }
+        text_property.culture_invariant_string =
+            Some(value.as_str().ok_or_else(invalid_value)?.to_string());
+    } else if let Some(byte_property) = cast!(Property, ByteProperty, property) {
+        // enum bytes take the index of the enum value
+        byte_property.value = value
+            .as_u64()
+            .and_then(|e| u8::try_from(e).ok())
+            .ok_or_else(invalid_value)?
+            .into();
+    } else if let Some(enum_property) = cast!(Property, EnumProperty, property) {
+        let enum_value = value.as_str().ok_or_else(invalid_value)?;
+        enum_property.value = asset.add_fname(enum_value);
+    } else if let Some(struct_property) = cast!(Property, StructProperty, property) {
+        for (nested_name, nested_value) in value.as_object().ok_or_else(invalid_value)? {
+            let nested_property = struct_property
+                .value
+                .iter_mut()
+                .find(|e| &e.get_name().content == nested_name)
+                .ok_or_else(|| {
+                    IntegratorError::invalid_value(
+                        &field_name,
+                        &format!("unknown struct field {}", nested_name),
+                    )
+                })?;
+            set_struct_field(asset, nested_property, nested_value)?;
+        }
+    } else {
+        return Err(IntegratorError::unsupported_property(property, &field_name).into());
+    }
+
+    Ok(())
+}
+
+/// Export and property indices of the arrays an entry name refers to.
+///
+/// `ExportName.ArrayName` only matches arrays on exports whose class is named `ExportName`.
+pub(crate) fn find_arrays(asset: &Asset, entry_name: &str) -> Vec<(usize, usize)> {
+    let (export_name, array_name) = match entry_name.contains('.') {
+        true => {
+            let split: Vec<&str> = entry_name.split('.').collect();
+            (Some(split[0]), split[1])
+        }
+        false => (None, entry_name),
+    };
+
+    let mut arrays = Vec::new();
+    for (i, export) in asset.exports.iter().enumerate() {
+        let normal_export = match export.get_normal_export() {
+            Some(normal_export) => normal_export,
+            None => continue,
+        };
+        if let Some(export_name) = export_name {
+            let class_index = normal_export.base_export.class_index;
+            if !class_index.is_import()
+                || asset
+                    .get_import(class_index)
+                    .map(|e| e.object_name.content != export_name)
+                    .unwrap_or(true)
+            {
+                continue;
+            }
+        }
+
+        for (j, property) in normal_export.properties.iter().enumerate() {
+            if let Some(array_property) = cast!(Property, ArrayProperty, property) {
+                if array_property.name.content == array_name {
+                    arrays.push((i, j));
+                }
+            }
+        }
+    }
+    arrays
+}
+
+/// Build a new struct element from the shape of the first element in the array
+fn build_struct_entry(
+    asset: &mut Asset,
+    export_index: usize,
+    property_index: usize,
+    fields: &serde_json::Map<String, serde_json::Value>,
+) -> Result<StructProperty, io::Error> {
+    let export =
+        cast!(Export, NormalExport, &asset.exports[export_index]).expect("Corrupted memory");
+    let array_property = cast!(Property, ArrayProperty, &export.properties[property_index])
+        .expect("Corrupted memory");
+    let array_name = array_property.name.content.clone();
+
+    let mut entry = array_property
+        .value
+        .iter()
+        .find_map(|e| cast!(Property, StructProperty, e))
+        .cloned()
+        .ok_or_else(|| {
+            IntegratorError::invalid_value(
+                &array_name,
+                "the array is empty, there's no element to copy the struct layout from",
+            )
+        })?;
+
+    for field_name in fields.keys() {
+        if !entry
+            .value
+            .iter()
+            .any(|e| &e.get_name().content == field_name)
+        {
+            return Err(IntegratorError::invalid_value(
+                &array_name,
+                &format!("unknown struct field {}", field_name),
+            )
+            .into());
+        }
+    }
+
+    for property in &mut entry.value {
+        match fields.get(&property.get_name().content) {
+            Some(value) => set_struct_field(asset, property, value)?,
+            None => warn!(
+                "Struct field {} not specified for array {}, keeping the existing value",
+                property.get_name().content,
+                array_name
+            ),
+        }
+    }
+
+    Ok(entry)
+}
 
 #[allow(clippy::ptr_arg)]
 pub(crate) fn handle_item_list_entries(
@@ -32,147 +286,264 @@ pub(crate) fn handle_item_list_entries(
     mod_paks: &mut Vec<PakFile>,
     item_list_entires_maps: &Vec<serde_json::Value>,
 ) -> Result<(), io::Error> {
-    let mut new_items = HashMap::new();
+    validate_metadata("item_list_entries", item_list_entires_maps)?;
 
-    for item_list_entries_map in item_list_entires_maps {
-        let item_list_entries_map = item_list_entries_map
-            .as_object()
-            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid item_list_entries"))?;
+    let mut new_items: NewItemListEntries = HashMap::new();
+
+    for (mod_index, item_list_entries_map) in item_list_entires_maps.iter().enumerate() {
+        let item_list_entries_map =
+            match expect_object("item_list_entries", mod_index, item_list_entries_map) {
+                Some(item_list_entries_map) => item_list_entries_map,
+                None => continue,
+            };
 
         for (name, item_list_entries) in item_list_entries_map {
             let item_list_entries = item_list_entries
                 .as_object()
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid item_list_entries"))?;
+                .ok_or_else(|| IntegratorError::invalid_metadata("item_list_entries", name))?;
             let new_items_entry = new_items.entry(name.clone()).or_insert_with(HashMap::new);
 
             for (item_name, entries) in item_list_entries {
-                let entries = entries
-                    .as_array()
-                    .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid item_list_entries"))?;
+                let field = format!("{}.{}", name, item_name);
+                let entries = entries.as_array().ok_or_else(|| {
+                    IntegratorError::invalid_metadata("item_list_entries", &field)
+                })?;
 
                 let new_items_entry_map = new_items_entry
                     .entry(item_name.clone())
                     .or_insert_with(Vec::new);
                 for entry in entries {
-                    let entry = entry.as_str().ok_or_else(|| {
-                        io::Error::new(ErrorKind::Other, "Invalid item_list_entries")
-                    })?;
-                    new_items_entry_map.push(String::from(entry));
+                    let entry = match entry {
+                        serde_json::Value::String(item_path) => {
+                            ItemListEntry::Path(item_path.clone())
+                        }
+                        serde_json::Value::Object(fields) => ItemListEntry::Struct(fields.clone()),
+                        _ => {
+                            return Err(IntegratorError::invalid_metadata(
+                                "item_list_entries",
+                                &field,
+                            )
+                            .into())
+                        }
+                    };
+                    new_items_entry_map.push((mod_index, entry));
                 }
             }
         }
     }
 
-    for (asset_name, entries) in &new_items {
-        let asset_name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, asset_name)
-            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
-        let mut asset = get_asset(integrated_pak, game_paks, mod_paks, &asset_name, VER_UE4_23)?;
+    append_item_list_entries(integrated_pak, game_paks, mod_paks, &new_items)
+}
+
+/// Element of a string or name array holding `value`, `None` for other array types
+fn value_entry(
+    array_type: &str,
+    name: FName,
+    value: &str,
+    add_fname: impl FnOnce(&str) -> FName,
+) -> Option<Property> {
+    match array_type {
+        "StrProperty" => Some(
+            StrProperty {
+                name,
+                property_guid: None,
+                duplication_index: 0,
+                value: Some(value.to_string()),
+            }
+            .into(),
+        ),
+        "NameProperty" => Some(
+            NameProperty {
+                name,
+                property_guid: None,
+                duplication_index: 0,
+                value: add_fname(value),
+            }
+            .into(),
+        ),
+        _ => None,
+    }
+}
+
+/// Property type of an empty, untyped array from the entries going into it.
+///
+/// Only item paths can be told apart from plain values, string and name arrays
+/// can't be typed without an existing element.
+fn infer_array_type(entries: &[(usize, ItemListEntry)]) -> Result<&'static str, String> {
+    for (mod_index, entry) in entries {
+        match entry {
+            ItemListEntry::Path(item_path) if item_path.contains('/') => {}
+            ItemListEntry::Path(value) => {
+                return Err(format!(
+                    "{:?} of mod #{} is a plain value and not an item path",
+                    value,
+                    mod_index + 1
+                ))
+            }
+            ItemListEntry::Struct(_) => {
+                return Err(format!(
+                    "mod #{} adds a struct, but there's no element to copy its type from",
+                    mod_index + 1
+                ))
+            }
+        }
+    }
+    Ok("ObjectProperty")
+}
+
+/// New entries of every mod, keyed by asset path and then by array name
+type NewItemListEntries = HashMap<String, HashMap<String, Vec<(usize, ItemListEntry)>>>;
+
+/// Append entries to arrays of existing assets
+fn append_item_list_entries(
+    integrated_pak: &mut PakFile,
+    game_paks: &mut Vec<PakFile>,
+    mod_paks: &mut Vec<PakFile>,
+    new_items: &NewItemListEntries,
+) -> Result<(), io::Error> {
+    let mut timings = AssetTimings::new("item_list_entries");
+    let mut report = HandlerReport::new("item_list_entries");
+
+    for (asset_name, entries) in new_items {
+        let asset_name = game_to_absolute(
+            AstroIntegratorConfig::GAME_NAME,
+            &normalize_content_path(asset_name),
+        )
+        .ok_or_else(|| IntegratorError::invalid_metadata("item_list_entries", asset_name))?;
+        let timer = timings.start();
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            asset_engine_version(&asset_name),
+        )?;
+        timings.record(&asset_name, AssetPhase::Read, timer);
+        let timer = timings.start();
 
         let mut item_types_property: HashMap<String, Vec<(usize, usize, String)>> = HashMap::new();
-        for i in 0..asset.exports.len() {
-            if let Some(normal_export) = asset.exports[i].get_normal_export() {
-                for j in 0..normal_export.properties.len() {
-                    let property = &normal_export.properties[j];
-                    for entry_name in entries.keys() {
-                        let mut arr_name = entry_name.clone();
-                        if arr_name.contains('.') {
-                            let split: Vec<&str> = arr_name.split('.').collect();
-                            let export_name = split[0].to_owned();
-                            arr_name = split[1].to_owned();
-
-                            if normal_export.base_export.class_index.is_import() {
-                                if asset
-                                    .get_import(normal_export.base_export.class_index)
-                                    .map(|e| e.object_name.content != export_name)
-                                    .unwrap_or(true)
-                                {
-                                    continue;
-                                }
-                            } else {
-                                continue;
-                            }
-                        }
-                        if let Some(array_property) = cast!(Property, ArrayProperty, property) {
-                            if array_property.name.content == arr_name {
-                                item_types_property
-                                    .entry(entry_name.clone())
-                                    .or_insert_with(Vec::new)
-                                    .push((
-                                        i,
-                                        j,
-                                        array_property
-                                            .array_type
-                                            .as_ref()
-                                            .ok_or_else(|| {
-                                                io::Error::new(
-                                                    ErrorKind::Other,
-                                                    "Invalid array_property",
-                                                )
-                                            })?
-                                            .content
-                                            .clone(),
-                                    ));
-                            }
-                        }
+        let mut untyped_arrays = Vec::new();
+        for entry_name in entries.keys() {
+            for (i, j) in find_arrays(&asset, entry_name) {
+                let array_property = asset.exports[i]
+                    .get_normal_export()
+                    .and_then(|e| cast!(Property, ArrayProperty, &e.properties[j]))
+                    .expect("Corrupted memory");
+                let array_type = match array_property.array_type.as_ref() {
+                    Some(array_type) => array_type.content.clone(),
+                    // an empty array might not have been typed yet
+                    None if array_property.value.is_empty() => {
+                        let array_type = infer_array_type(&entries[entry_name]).map_err(|e| {
+                            IntegratorError::invalid_value(
+                                &format!("{} in {}", entry_name, asset_name),
+                                &e,
+                            )
+                        })?;
+                        untyped_arrays.push((i, j, array_type));
+                        String::from(array_type)
                     }
-                }
+                    None => {
+                        return Err(IntegratorError::corrupted_asset(
+                            &asset_name,
+                            &format!("array {} has elements but no type", entry_name),
+                        )
+                        .into())
+                    }
+                };
+                item_types_property
+                    .entry(entry_name.clone())
+                    .or_insert_with(Vec::new)
+                    .push((i, j, array_type));
             }
         }
+
+        for (export_index, property_index, array_type) in untyped_arrays {
+            let array_type_name = asset.add_fname(array_type);
+
+            let export = cast!(Export, NormalExport, &mut asset.exports[export_index])
+                .expect("Corrupted memory");
+            let property = cast!(
+                Property,
+                ArrayProperty,
+                &mut export.properties[property_index]
+            )
+            .expect("Corrupted memory");
+            debug!(
+                "Setting array type of untyped array {} in {} to {}",
+                property.name.content, asset_name, array_type
+            );
+            property.array_type = Some(array_type_name);
+        }
+
         for (name, item_paths) in entries {
             if !item_types_property.contains_key(name) {
+                warn!("Failed to find array {} in {}", name, asset_name);
+                for (mod_index, _) in item_paths {
+                    report.skipped(*mod_index);
+                }
                 continue;
             }
-            for item_path in item_paths {
-                let (real_name, class_name, soft_class_name) = match item_path.contains('.') {
-                    true => {
-                        let split: Vec<&str> = item_path.split('.').collect();
-                        (
-                            split[0].to_string(),
-                            split[1].to_string(),
-                            split[1].to_string(),
-                        )
+            for (mod_index, item) in item_paths {
+                let item_path = match item {
+                    ItemListEntry::Path(item_path) => item_path,
+                    ItemListEntry::Struct(fields) => {
+                        for (export_index, property_index, array_type) in
+                            item_types_property.get(name).unwrap()
+                        {
+                            if array_type != "StructProperty" {
+                                warn!(
+                                    "Struct entry for {} in {} can't be added to a {} array",
+                                    name, asset_name, array_type
+                                );
+                                report.skipped(*mod_index);
+                                continue;
+                            }
+
+                            let entry = build_struct_entry(
+                                &mut asset,
+                                *export_index,
+                                *property_index,
+                                fields,
+                            )?;
+
+                            let export =
+                                cast!(Export, NormalExport, &mut asset.exports[*export_index])
+                                    .expect("Corrupted memory");
+                            let property = cast!(
+                                Property,
+                                ArrayProperty,
+                                &mut export.properties[*property_index]
+                            )
+                            .expect("Corrupted memory");
+                            property.value.push(entry.into());
+                            report.applied(
+                                *mod_index,
+                                &asset_name,
+                                Change::Added,
+                                &format!("struct entry to {}", name),
+                            );
+                        }
+                        continue;
                     }
-                    false => (
-                        item_path.clone(),
-                        Path::new(item_path)
-                            .file_stem()
-                            .and_then(|e| e.to_str())
-                            .map(|e| String::from(e) + "_C")
-                            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid item_path"))?,
-                        Path::new(item_path)
-                            .file_stem()
-                            .and_then(|e| e.to_str())
-                            .map(|e| e.to_string())
-                            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid item_path"))?,
-                    ),
+                };
+                let arrays = item_types_property.get(name).unwrap();
+
+                // string and name entries are plain values, not paths
+                let (real_name, class_name, soft_class_name) = match arrays
+                    .iter()
+                    .any(|(_, _, e)| e == "ObjectProperty" || e == "SoftObjectProperty")
+                {
+                    true => split_item_path(&normalize_content_path(item_path))?,
+                    false => Default::default(),
                 };
 
                 let mut new_import = PackageIndex::new(0);
 
-                for (export_index, property_index, array_type) in
-                    item_types_property.get(name).unwrap()
-                {
+                for (export_index, property_index, array_type) in arrays {
                     match array_type.as_str() {
                         "ObjectProperty" => {
                             if new_import.index == 0 {
-                                asset.add_name_reference(real_name.clone(), false);
-                                asset.add_name_reference(class_name.clone(), false);
-
-                                let inner_import = Import {
-                                    class_package: FName::from_slice("/Script/CoreUObject"),
-                                    class_name: FName::from_slice("Package"),
-                                    outer_index: PackageIndex::new(0),
-                                    object_name: FName::new(real_name.clone(), 0),
-                                };
-                                let inner_import = asset.add_import(inner_import);
-
-                                let import = Import {
-                                    class_package: FName::from_slice("/Script/Engine"),
-                                    class_name: FName::from_slice("BlueprintGeneratedClass"),
-                                    outer_index: inner_import,
-                                    object_name: FName::new(class_name.clone(), 0),
-                                };
-                                new_import = asset.add_import(import);
+                                new_import = add_class_import(&mut asset, &real_name, &class_name)?;
                             }
 
                             let export =
@@ -193,13 +564,17 @@ pub(crate) fn handle_item_list_entries(
                                 }
                                 .into(),
                             );
+                            report.applied(
+                                *mod_index,
+                                &asset_name,
+                                Change::Added,
+                                &format!("{} to {}", item_path, name),
+                            );
                         }
                         "SoftObjectProperty" => {
+                            let soft_object_path = real_name.clone() + "." + &soft_class_name;
                             asset.add_name_reference(real_name.clone(), false);
-                            asset.add_name_reference(
-                                real_name.clone() + "." + &soft_class_name,
-                                false,
-                            );
+                            asset.add_name_reference(soft_object_path.clone(), false);
 
                             let export =
                                 cast!(Export, NormalExport, &mut asset.exports[*export_index])
@@ -215,24 +590,134 @@ pub(crate) fn handle_item_list_entries(
                                     name: property.name.clone(),
                                     property_guid: None,
                                     duplication_index: 0,
-                                    value: FName::new(
-                                        real_name.clone() + "." + &soft_class_name,
-                                        0,
-                                    ),
+                                    value: FName::new(soft_object_path, 0),
+                                    // id is the serialized sub path string of the soft object path,
+                                    // item references point at the asset itself so there is no sub path
                                     id: 0,
                                 }
                                 .into(),
                             );
+                            report.applied(
+                                *mod_index,
+                                &asset_name,
+                                Change::Added,
+                                &format!("{} to {}", item_path, name),
+                            );
                         }
-                        _ => {}
+                        array_type @ ("StrProperty" | "NameProperty") => {
+                            let property_name = asset.exports[*export_index]
+                                .get_normal_export()
+                                .and_then(|e| {
+                                    cast!(Property, ArrayProperty, &e.properties[*property_index])
+                                })
+                                .expect("Corrupted memory")
+                                .name
+                                .clone();
+                            let entry = value_entry(array_type, property_name, item_path, |e| {
+                                asset.add_fname(e)
+                            })
+                            .expect("Corrupted memory");
+
+                            let export =
+                                cast!(Export, NormalExport, &mut asset.exports[*export_index])
+                                    .expect("Corrupted memory");
+                            let property = cast!(
+                                Property,
+                                ArrayProperty,
+                                &mut export.properties[*property_index]
+                            )
+                            .expect("Corrupted memory");
+                            property.value.push(entry);
+                            report.applied(
+                                *mod_index,
+                                &asset_name,
+                                Change::Added,
+                                &format!("{} to {}", item_path, name),
+                            );
+                        }
+                        _ => report.skipped(*mod_index),
                     }
                 }
             }
         }
 
-        write_asset(integrated_pak, &asset, &asset_name)
-            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+        let array_locations: Vec<(usize, usize)> = item_types_property
+            .values()
+            .flatten()
+            .map(|(export_index, property_index, _)| (*export_index, *property_index))
+            .collect();
+        verify_soft_object_names(&asset, &asset_name, &array_locations)?;
+        timings.record(&asset_name, AssetPhase::Modify, timer);
+
+        let timer = timings.start();
+        write_integrated_asset(integrated_pak, &asset, &asset_name)?;
+        timings.record(&asset_name, AssetPhase::Write, timer);
     }
 
+    timings.finish();
+    report.log();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use unreal_modloader::unreal_asset::{cast, properties::Property, unreal_types::FName};
+
+    use super::{infer_array_type, value_entry, ItemListEntry};
+
+    fn path(item_path: &str) -> (usize, ItemListEntry) {
+        (0, ItemListEntry::Path(item_path.to_string()))
+    }
+
+    #[test]
+    fn item_paths_type_an_object_array() {
+        let entries = [
+            path("/Game/Items/ItemTypes/Iron.Iron_C"),
+            path("/Game/Items/ItemTypes/Copper"),
+        ];
+        assert_eq!(infer_array_type(&entries), Ok("ObjectProperty"));
+    }
+
+    #[test]
+    fn plain_values_are_rejected() {
+        let entries = [path("/Game/Items/ItemTypes/Iron"), path("Iron")];
+        assert!(infer_array_type(&entries).is_err());
+    }
+
+    #[test]
+    fn struct_entries_are_rejected() {
+        let entries = [(0, ItemListEntry::Struct(serde_json::Map::new()))];
+        assert!(infer_array_type(&entries).is_err());
+    }
+
+    #[test]
+    fn string_entries_keep_the_value() {
+        let entry = value_entry("StrProperty", FName::from_slice("Names"), "Iron", |_| {
+            panic!("strings aren't names")
+        })
+        .unwrap();
+        let entry = cast!(Property, StrProperty, &entry).unwrap();
+        assert_eq!(entry.name.content, "Names");
+        assert_eq!(entry.value.as_deref(), Some("Iron"));
+    }
+
+    #[test]
+    fn name_entries_register_the_value() {
+        let entry = value_entry("NameProperty", FName::from_slice("Names"), "Iron", |e| {
+            FName::from_slice(e)
+        })
+        .unwrap();
+        let entry = cast!(Property, NameProperty, &entry).unwrap();
+        assert_eq!(entry.value.content, "Iron");
+    }
+
+    #[test]
+    fn object_arrays_have_no_value_entries() {
+        assert!(
+            value_entry("ObjectProperty", FName::from_slice("Items"), "Iron", |e| {
+                FName::from_slice(e)
+            })
+            .is_none()
+        );
+    }
+}
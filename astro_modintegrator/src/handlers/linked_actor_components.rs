@@ -1,8 +1,4 @@
-use std::{
-    collections::HashMap,
-    io::{self, ErrorKind},
-    path::Path,
-};
+use std::{collections::HashMap, io, path::Path};
 
 use unreal_modloader::unreal_asset::{
     cast,
@@ -13,23 +9,80 @@ use unreal_modloader::unreal_asset::{
         str_property::NameProperty, struct_property::StructProperty, Property, PropertyDataTrait,
     },
     reader::asset_trait::AssetTrait,
-    ue4version::VER_UE4_23,
     unreal_types::{FName, PackageIndex},
     uproperty::UProperty,
     Asset, Import,
 };
 use unreal_modloader::unreal_modintegrator::{
     helpers::{game_to_absolute, get_asset},
-    write_asset, IntegratorConfig,
+    IntegratorConfig,
 };
 use unreal_modloader::unreal_pak::PakFile;
 use uuid::Uuid;
 
+use super::report::{Change, HandlerReport};
+use super::timing::{AssetPhase, AssetTimings};
+use super::{expect_object, normalize_content_path, verify_import_chain, write_integrated_asset};
 use crate::{
-    assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT},
+    assets::{
+        asset_engine_version, check_template_engine_version, ACTOR_TEMPLATE_ASSET,
+        ACTOR_TEMPLATE_EXPORT, TEMPLATE_ENGINE_VERSION,
+    },
+    error::IntegratorError,
+    schema::validate_metadata,
+    settings::SETTINGS,
     AstroIntegratorConfig,
 };
 
+/// Guid of the SCS node added for a component.
+///
+/// Derived from the actor and component paths and the index of the node's name,
+/// so integrating the same mods gives the same pak while two mods linking the same
+/// component to an actor still get different guids.
+/// Random guids can be enabled in the settings instead.
+fn variable_guid(
+    actor_path: &str,
+    component_path: &str,
+    component_name: &str,
+    scs_node_name_index: i32,
+) -> [u8; 16] {
+    match SETTINGS.deterministic_guids {
+        true => deterministic_guid(
+            actor_path,
+            component_path,
+            component_name,
+            scs_node_name_index,
+        ),
+        false => Uuid::new_v4().into_bytes(),
+    }
+}
+
+fn deterministic_guid(
+    actor_path: &str,
+    component_path: &str,
+    component_name: &str,
+    scs_node_name_index: i32,
+) -> [u8; 16] {
+    Uuid::new_v5(
+        &Uuid::NAMESPACE_URL,
+        format!(
+            "{}:{}.{}#{}",
+            actor_path, component_path, component_name, scs_node_name_index
+        )
+        .as_bytes(),
+    )
+    .into_bytes()
+}
+
+/// Name index for a new `SCS_Node`, one past the highest index an existing node uses
+fn next_scs_node_name_index<'a>(object_names: impl Iterator<Item = &'a FName>) -> i32 {
+    object_names
+        .filter(|e| e.content == "SCS_Node")
+        .map(|e| e.index)
+        .fold(0, i32::max)
+        + 1
+}
+
 #[allow(clippy::ptr_arg)]
 pub(crate) fn handle_linked_actor_components(
     _data: &(),
@@ -38,14 +91,16 @@ pub(crate) fn handle_linked_actor_components(
     mod_paks: &mut Vec<PakFile>,
     linked_actors_maps: &Vec<serde_json::Value>,
 ) -> Result<(), io::Error> {
+    validate_metadata("linked_actor_components", linked_actors_maps)?;
+
     let mut actor_asset = Asset::new(
         ACTOR_TEMPLATE_ASSET.to_vec(),
         Some(ACTOR_TEMPLATE_EXPORT.to_vec()),
     );
-    actor_asset.engine_version = VER_UE4_23;
+    actor_asset.engine_version = TEMPLATE_ENGINE_VERSION;
     actor_asset
         .parse_data()
-        .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+        .map_err(|e| IntegratorError::asset_data("ActorTemplate", e))?;
 
     let gen_variable =
         cast!(Export, NormalExport, &actor_asset.exports[0]).expect("Corrupted ActorTemplate");
@@ -56,31 +111,50 @@ pub(crate) fn handle_linked_actor_components(
 
     let mut new_components = HashMap::new();
 
-    for linked_actor_map in linked_actors_maps {
-        let linked_actors_map = linked_actor_map
-            .as_object()
-            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid linked_actor_components"))?;
+    for (mod_index, linked_actor_map) in linked_actors_maps.iter().enumerate() {
+        let linked_actors_map =
+            match expect_object("linked_actor_components", mod_index, linked_actor_map) {
+                Some(linked_actors_map) => linked_actors_map,
+                None => continue,
+            };
         for (name, components) in linked_actors_map.iter() {
             let components = components.as_array().ok_or_else(|| {
-                io::Error::new(ErrorKind::Other, "Invalid linked_actor_components")
+                IntegratorError::invalid_metadata("linked_actor_components", name)
             })?;
 
             let entry = new_components.entry(name.clone()).or_insert_with(Vec::new);
             for component in components {
                 let component_name = component.as_str().ok_or_else(|| {
-                    io::Error::new(ErrorKind::Other, "Invalid linked_actor_components")
+                    IntegratorError::invalid_metadata("linked_actor_components", name)
                 })?;
-                entry.push(String::from(component_name));
+                entry.push((mod_index, normalize_content_path(component_name)));
             }
         }
     }
 
-    for (name, components) in &new_components {
-        let name = game_to_absolute(AstroIntegratorConfig::GAME_NAME, name)
-            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid asset name"))?;
-        let mut asset = get_asset(integrated_pak, game_paks, mod_paks, &name, VER_UE4_23)?;
+    let mut timings = AssetTimings::new("linked_actor_components");
+    let mut report = HandlerReport::new("linked_actor_components");
 
-        for component_path_raw in components {
+    for (name, components) in &new_components {
+        let name = game_to_absolute(
+            AstroIntegratorConfig::GAME_NAME,
+            &normalize_content_path(name),
+        )
+        .ok_or_else(|| IntegratorError::invalid_metadata("linked_actor_components", name))?;
+        // the template's exports are copied into the actor
+        check_template_engine_version(asset_engine_version(&name))?;
+        let timer = timings.start();
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &name,
+            asset_engine_version(&name),
+        )?;
+        timings.record(&name, AssetPhase::Read, timer);
+        let timer = timings.start();
+
+        for (mod_index, component_path_raw) in components {
             let mut actor_index = None;
             let mut simple_construction_script = None;
             let mut cdo_location = None;
@@ -90,7 +164,12 @@ pub(crate) fn handle_linked_actor_components(
                     if normal_export.base_export.class_index.is_import() {
                         let import = asset
                             .get_import(normal_export.base_export.class_index)
-                            .ok_or_else(|| io::Error::new(ErrorKind::Other, "Import not found"))?;
+                            .ok_or_else(|| {
+                                IntegratorError::corrupted_asset(
+                                    &name,
+                                    "export class import not found",
+                                )
+                            })?;
                         match import.object_name.content.as_str() {
                             "BlueprintGeneratedClass" => actor_index = Some(i),
                             "SimpleConstructionScript" => simple_construction_script = Some(i),
@@ -100,7 +179,7 @@ pub(crate) fn handle_linked_actor_components(
                     if (EObjectFlags::RF_CLASS_DEFAULT_OBJECT
                         & EObjectFlags::from_bits(normal_export.base_export.object_flags)
                             .ok_or_else(|| {
-                                io::Error::new(ErrorKind::Other, "Invalid object flags")
+                                IntegratorError::corrupted_asset(&name, "invalid object flags")
                             })?)
                         == EObjectFlags::RF_CLASS_DEFAULT_OBJECT
                     {
@@ -109,14 +188,16 @@ pub(crate) fn handle_linked_actor_components(
                 }
             }
 
-            let actor_index =
-                actor_index.ok_or_else(|| io::Error::new(ErrorKind::Other, "Actor not found"))?;
+            let actor_index = actor_index.ok_or_else(|| {
+                IntegratorError::missing_export(&name, "BlueprintGeneratedClass export")
+            })?;
             let actor = actor_index as i32 + 1;
-            let simple_construction_script_index = simple_construction_script
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "SCS not found"))?;
+            let simple_construction_script_index = simple_construction_script.ok_or_else(|| {
+                IntegratorError::missing_export(&name, "SimpleConstructionScript export")
+            })?;
             let simple_construction_script = simple_construction_script_index as i32 + 1;
-            let cdo_location =
-                cdo_location.ok_or_else(|| io::Error::new(ErrorKind::Other, "CDO not found"))?;
+            let cdo_location = cdo_location
+                .ok_or_else(|| IntegratorError::missing_export(&name, "class default object"))?;
 
             let class_object_property_import = asset
                 .find_import_no_index(
@@ -153,7 +234,7 @@ pub(crate) fn handle_linked_actor_components(
             let component = Path::new(component_path_raw)
                 .file_stem()
                 .and_then(|e| e.to_str())
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid persistent actors"))?;
+                .ok_or_else(|| IntegratorError::invalid_path(component_path_raw))?;
 
             let (component_path_raw, component) = match component.contains('.') {
                 true => {
@@ -189,11 +270,14 @@ pub(crate) fn handle_linked_actor_components(
                 object_name: asset.add_fname(&default_component),
             };
             let default_import = asset.add_import(default_import);
+            verify_import_chain(&asset, blueprint_generated_class_import)?;
+            verify_import_chain(&asset, default_import)?;
 
             let mut component_export = component_export.clone();
             let component_object_property =
-                cast!(UProperty, UObjectProperty, &mut component_export.property)
-                    .ok_or_else(|| io::Error::new(ErrorKind::Other, "Corrupted starter pak"))?;
+                cast!(UProperty, UObjectProperty, &mut component_export.property).ok_or_else(
+                    || IntegratorError::corrupted_asset("ActorTemplate", "unexpected exports"),
+                )?;
             component_object_property.property_class = blueprint_generated_class_import;
 
             let component_base_export = component_export.get_base_export_mut();
@@ -252,10 +336,17 @@ pub(crate) fn handle_linked_actor_components(
             asset.exports.push(component_gen_variable.into());
             let component_gen_variable_index = asset.exports.len() as i32;
 
+            let scs_node_name_index = next_scs_node_name_index(
+                asset
+                    .exports
+                    .iter()
+                    .map(|e| &e.get_base_export().object_name),
+            );
+
             let mut scs_node = scs_export.clone();
-            let scs_node_normal_export = scs_node
-                .get_normal_export_mut()
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Corrupted starter pak"))?;
+            let scs_node_normal_export = scs_node.get_normal_export_mut().ok_or_else(|| {
+                IntegratorError::corrupted_asset("ActorTemplate", "unexpected exports")
+            })?;
             scs_node_normal_export.properties = Vec::from([
                 ObjectProperty {
                     name: asset.add_fname("ComponentClass"),
@@ -282,7 +373,12 @@ pub(crate) fn handle_linked_actor_components(
                         name: asset.add_fname("VariableGuid"),
                         property_guid: None,
                         duplication_index: 0,
-                        value: Uuid::new_v4().into_bytes(),
+                        value: variable_guid(
+                            &name,
+                            &component_path_raw,
+                            component,
+                            scs_node_name_index,
+                        ),
                     }
                     .into()]),
                 }
@@ -317,15 +413,8 @@ pub(crate) fn handle_linked_actor_components(
                 .create_before_create_dependencies =
                 Vec::from([PackageIndex::new(simple_construction_script)]);
 
-            let mut last_scs_node_index = 0;
-            for export in &asset.exports {
-                let object_name = &export.get_base_export().object_name;
-                if object_name.content == "SCS_Node" && last_scs_node_index < object_name.index {
-                    last_scs_node_index = object_name.index;
-                }
-            }
             scs_node_normal_export.base_export.object_name =
-                FName::new("SCS_Node".to_string(), last_scs_node_index + 1);
+                FName::new("SCS_Node".to_string(), scs_node_name_index);
 
             asset.exports.push(scs_node.into());
             let scs_node_index = asset.exports.len() as i32;
@@ -370,10 +459,70 @@ pub(crate) fn handle_linked_actor_components(
                     }
                 }
             }
+
+            report.applied(
+                *mod_index,
+                &name,
+                Change::Added,
+                &format!("component {}", component),
+            );
         }
 
-        write_asset(integrated_pak, &asset, &name)
-            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+        timings.record(&name, AssetPhase::Modify, timer);
+
+        let timer = timings.start();
+        write_integrated_asset(integrated_pak, &asset, &name)?;
+        timings.record(&name, AssetPhase::Write, timer);
     }
+
+    timings.finish();
+    report.log();
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use unreal_modloader::unreal_asset::unreal_types::FName;
+
+    use super::{deterministic_guid, next_scs_node_name_index};
+
+    const ACTOR: &str = "/Game/Items/Actor";
+    const COMPONENT: &str = "/Game/Mods/Component";
+
+    #[test]
+    fn guids_are_stable() {
+        assert_eq!(
+            deterministic_guid(ACTOR, COMPONENT, "Component", 3),
+            deterministic_guid(ACTOR, COMPONENT, "Component", 3)
+        );
+    }
+
+    #[test]
+    fn guids_differ_per_scs_node() {
+        assert_ne!(
+            deterministic_guid(ACTOR, COMPONENT, "Component", 3),
+            deterministic_guid(ACTOR, COMPONENT, "Component", 4)
+        );
+    }
+
+    #[test]
+    fn guids_differ_per_actor() {
+        assert_ne!(
+            deterministic_guid(ACTOR, COMPONENT, "Component", 3),
+            deterministic_guid("/Game/Items/OtherActor", COMPONENT, "Component", 3)
+        );
+    }
+
+    #[test]
+    fn new_scs_nodes_follow_the_highest_existing_index() {
+        let names = [
+            FName::new(String::from("SCS_Node"), 2),
+            FName::new(String::from("SCS_Node"), 7),
+            FName::new(String::from("Default__Actor_C"), 12),
+            FName::new(String::from("SCS_Node"), 4),
+        ];
+        assert_eq!(next_scs_node_name_index(names.iter()), 8);
+        assert_eq!(next_scs_node_name_index(names[2..3].iter()), 1);
+        assert_eq!(next_scs_node_name_index([].iter()), 1);
+    }
+}
@@ -1,21 +1,28 @@
-use std::{
-    io::{self, ErrorKind},
-    path::Path,
-};
+use log::{info, warn};
+use std::{io, path::Path};
 
 use unreal_modloader::unreal_asset::{
     cast,
     exports::{Export, ExportNormalTrait},
     properties::{object_property::ObjectProperty, Property},
     reader::asset_trait::AssetTrait,
-    ue4version::VER_UE4_23,
     unreal_types::{FName, PackageIndex},
     Import,
 };
-use unreal_modloader::unreal_modintegrator::{helpers::get_asset, write_asset};
+use unreal_modloader::unreal_modintegrator::helpers::get_asset;
 use unreal_modloader::unreal_pak::PakFile;
 
-use super::MAP_PATHS;
+use crate::assets::asset_engine_version;
+use crate::error::IntegratorError;
+use crate::schema::validate_metadata;
+use crate::settings::SETTINGS;
+
+use super::report::{Change, HandlerReport};
+use super::timing::{AssetPhase, AssetTimings};
+use super::{
+    expect_array, handler_map_paths, is_excluded_map, normalize_content_path, verify_import_chain,
+    write_integrated_asset,
+};
 
 #[allow(clippy::ptr_arg)]
 pub(crate) fn handle_mission_trailheads(
@@ -25,30 +32,70 @@ pub(crate) fn handle_mission_trailheads(
     mod_paks: &mut Vec<PakFile>,
     trailhead_arrays: &Vec<serde_json::Value>,
 ) -> Result<(), io::Error> {
-    for map_path in MAP_PATHS {
+    validate_metadata("mission_trailheads", trailhead_arrays)?;
+
+    let mut trailheads = Vec::new();
+    for (mod_index, value) in trailhead_arrays.iter().enumerate() {
+        let trailheads_array = match expect_array("mission_trailheads", mod_index, value) {
+            Some(trailheads_array) => trailheads_array,
+            None => continue,
+        };
+        for trailhead in trailheads_array {
+            // either just the path, or the path with maps to leave out
+            let (trailhead, exclude_maps) = match trailhead {
+                serde_json::Value::Object(trailhead) => (
+                    trailhead.get("trailhead").and_then(|e| e.as_str()),
+                    trailhead
+                        .get("exclude_maps")
+                        .and_then(|e| e.as_array())
+                        .map(|e| {
+                            e.iter()
+                                .filter_map(|e| e.as_str())
+                                .map(String::from)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                ),
+                trailhead => (trailhead.as_str(), Vec::new()),
+            };
+            let trailhead = trailhead.ok_or_else(|| {
+                IntegratorError::invalid_metadata(
+                    "mission_trailheads",
+                    &format!("trailhead of mod #{}", mod_index + 1),
+                )
+            })?;
+            trailheads.push((mod_index, normalize_content_path(trailhead), exclude_maps));
+        }
+    }
+
+    let mut timings = AssetTimings::new("mission_trailheads");
+    let mut report = HandlerReport::new("mission_trailheads");
+
+    for map_path in handler_map_paths("mission_trailheads") {
+        let trailheads: Vec<(usize, &String)> = trailheads
+            .iter()
+            .filter(|(_, trailhead, exclude_maps)| {
+                let excluded = is_excluded_map(map_path, exclude_maps);
+                if excluded {
+                    info!("Not adding {} to excluded map {}", trailhead, map_path);
+                }
+                !excluded
+            })
+            .map(|(mod_index, trailhead, _)| (*mod_index, trailhead))
+            .collect();
+        let timer = timings.start();
         let mut asset = get_asset(
             integrated_pak,
             game_paks,
             mod_paks,
             &String::from(map_path),
-            VER_UE4_23,
+            asset_engine_version(map_path),
         )?;
+        timings.record(map_path, AssetPhase::Read, timer);
+        let timer = timings.start();
 
-        let mut trailheads = Vec::new();
-        for trailheads_array in trailhead_arrays {
-            let trailheads_array = trailheads_array
-                .as_array()
-                .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid trailheads"))?;
-            for trailhead in trailheads_array {
-                let trailhead = trailhead
-                    .as_str()
-                    .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid trailheads"))?;
-                trailheads.push(trailhead);
-            }
-        }
-
-        let mut mission_data_export_index = None;
-        let mut mission_data_property_index = None;
+        // (preference, export index, property index) of the best matching mission data array
+        let mut mission_data = None;
 
         for i in 0..asset.exports.len() {
             let export = &asset.exports[i];
@@ -56,22 +103,39 @@ pub(crate) fn handle_mission_trailheads(
                 if normal_export.base_export.class_index.is_import() {
                     let import = asset
                         .get_import(normal_export.base_export.class_index)
-                        .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid import"))?;
-                    if import.object_name.content == "AstroSettings" {
-                        for j in 0..normal_export.properties.len() {
-                            let property = &normal_export.properties[j];
-                            if let Some(array_property) = cast!(Property, ArrayProperty, property) {
-                                if array_property.name.content == "MissionData"
-                                    && array_property
-                                        .array_type
-                                        .as_ref()
-                                        .map(|e| e.content == "ObjectProperty")
-                                        .unwrap_or(false)
-                                {
-                                    mission_data_export_index = Some(i);
-                                    mission_data_property_index = Some(j);
-                                    break;
-                                }
+                        .ok_or_else(|| {
+                            IntegratorError::corrupted_asset(
+                                map_path,
+                                "export class import not found",
+                            )
+                        })?;
+                    let preference = match SETTINGS
+                        .mission_data_exports
+                        .iter()
+                        .position(|e| *e == import.object_name.content)
+                    {
+                        Some(preference) => preference,
+                        None => continue,
+                    };
+                    if mission_data
+                        .map(|(best, _, _)| preference >= best)
+                        .unwrap_or(false)
+                    {
+                        continue;
+                    }
+
+                    for j in 0..normal_export.properties.len() {
+                        let property = &normal_export.properties[j];
+                        if let Some(array_property) = cast!(Property, ArrayProperty, property) {
+                            if array_property.name.content == "MissionData"
+                                && array_property
+                                    .array_type
+                                    .as_ref()
+                                    .map(|e| e.content == "ObjectProperty")
+                                    .unwrap_or(false)
+                            {
+                                mission_data = Some((preference, i, j));
+                                break;
                             }
                         }
                     }
@@ -79,14 +143,36 @@ pub(crate) fn handle_mission_trailheads(
             }
         }
 
-        if let (Some(mission_data_export_index), Some(mission_data_property_index)) =
-            (mission_data_export_index, mission_data_property_index)
+        if mission_data.is_none() && !trailheads.is_empty() {
+            warn!(
+                "No mission data found in {}, looked for {}",
+                map_path,
+                SETTINGS.mission_data_exports.join(", ")
+            );
+            for (mod_index, _) in &trailheads {
+                report.skipped(*mod_index);
+            }
+        }
+
+        if let Some((preference, mission_data_export_index, mission_data_property_index)) =
+            mission_data
         {
-            for trailhead in trailheads {
-                let soft_class_name = Path::new(trailhead)
+            if !trailheads.is_empty() {
+                info!(
+                    "Adding {} trailheads to {} in {}",
+                    trailheads.len(),
+                    SETTINGS.mission_data_exports[preference],
+                    map_path
+                );
+            }
+
+            for (mod_index, trailhead) in &trailheads {
+                let soft_class_name = Path::new(trailhead.as_str())
                     .file_stem()
                     .and_then(|e| e.to_str())
-                    .ok_or_else(|| io::Error::new(ErrorKind::Other, "Invalid trailhead"))?;
+                    .ok_or_else(|| {
+                        IntegratorError::invalid_metadata("mission_trailheads", trailhead)
+                    })?;
                 asset.add_fname(trailhead);
                 asset.add_fname(soft_class_name);
 
@@ -105,6 +191,7 @@ pub(crate) fn handle_mission_trailheads(
                     object_name: FName::from_slice(soft_class_name),
                 };
                 let mission_data_asset_link = asset.add_import(mission_data_asset_link);
+                verify_import_chain(&asset, mission_data_asset_link)?;
 
                 let mission_data_export = cast!(
                     Export,
@@ -126,12 +213,23 @@ pub(crate) fn handle_mission_trailheads(
                     value: mission_data_asset_link,
                 };
                 mission_data_property.value.push(property.into());
+                report.applied(
+                    *mod_index,
+                    map_path,
+                    Change::Added,
+                    &format!("trailhead {}", trailhead),
+                );
             }
         }
 
-        write_asset(integrated_pak, &asset, &String::from(map_path))
-            .map_err(|e| io::Error::new(ErrorKind::Other, e.to_string()))?;
+        timings.record(map_path, AssetPhase::Modify, timer);
+
+        let timer = timings.start();
+        write_integrated_asset(integrated_pak, &asset, &String::from(map_path))?;
+        timings.record(map_path, AssetPhase::Write, timer);
     }
 
+    timings.finish();
+    report.log();
     Ok(())
 }
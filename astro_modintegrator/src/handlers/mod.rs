@@ -1,19 +1,659 @@
-use lazy_static::lazy_static;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{self, Cursor},
+    path::Path,
+};
 
+use lazy_static::lazy_static;
+use log::{info, warn};
 use regex::Regex;
+use serde::Deserialize;
+use unreal_modloader::unreal_asset::{
+    cast,
+    exports::{Export, ExportBaseTrait, ExportNormalTrait},
+    properties::{Property, PropertyDataTrait},
+    reader::asset_trait::AssetTrait,
+    unreal_types::PackageIndex,
+    Asset, Import,
+};
+use unreal_modloader::unreal_modintegrator::write_asset;
+use unreal_modloader::unreal_pak::PakFile;
+
+use crate::error::IntegratorError;
+use crate::settings::SETTINGS;
 
 pub(crate) mod biome_placement_modifiers;
+pub(crate) mod data_table_entries;
 pub(crate) mod item_list_entries;
 pub(crate) mod linked_actor_components;
 pub(crate) mod mission_trailheads;
+pub(crate) mod patch_array_entries;
+pub(crate) mod report;
+pub(crate) mod string_table_entries;
+pub(crate) mod timing;
 
 lazy_static! {
     static ref GAME_REGEX: Regex = Regex::new(r"^/Game/").unwrap();
 }
 
-pub(crate) static MAP_PATHS: [&str; 3] = [
-    "Astro/Content/Maps/Staging_T2.umap",
-    "Astro/Content/Maps/Staging_T2_PackedPlanets_Switch.umap",
-    //"Astro/Content/Maps/TutorialMoon_Prototype_v2.umap", // Tutorial not integrated for performance
-    "Astro/Content/Maps/test/BasicSphereT2.umap",
-];
+/// Name of a json value's type, for messages about metadata of the wrong type
+fn json_type_name(value: &serde_json::Value) -> &'static str {
+    match value {
+        serde_json::Value::Null => "null",
+        serde_json::Value::Bool(_) => "a boolean",
+        serde_json::Value::Number(_) => "a number",
+        serde_json::Value::String(_) => "a string",
+        serde_json::Value::Array(_) => "an array",
+        serde_json::Value::Object(_) => "an object",
+    }
+}
+
+/// A mod's metadata for a handler as an array,
+/// mods with metadata of the wrong type are skipped instead of failing the integration
+pub(crate) fn expect_array<'a>(
+    handler_name: &str,
+    mod_index: usize,
+    value: &'a serde_json::Value,
+) -> Option<&'a Vec<serde_json::Value>> {
+    if value.is_array() {
+        return value.as_array();
+    }
+    warn!(
+        "Skipping {} of mod #{}, expected an array but got {}",
+        handler_name,
+        mod_index + 1,
+        json_type_name(value)
+    );
+    None
+}
+
+/// A mod's metadata for a handler as an object, see [`expect_array`]
+pub(crate) fn expect_object<'a>(
+    handler_name: &str,
+    mod_index: usize,
+    value: &'a serde_json::Value,
+) -> Option<&'a serde_json::Map<String, serde_json::Value>> {
+    if value.is_object() {
+        return value.as_object();
+    }
+    warn!(
+        "Skipping {} of mod #{}, expected an object but got {}",
+        handler_name,
+        mod_index + 1,
+        json_type_name(value)
+    );
+    None
+}
+
+/// Check that the outer chain of an added import ends at a package import.
+///
+/// A chain that ends anywhere else makes the game fail to resolve the import,
+/// so it is caught here with the whole chain in the error.
+pub(crate) fn verify_import_chain(asset: &Asset, index: PackageIndex) -> Result<(), io::Error> {
+    let mut chain = Vec::new();
+    let mut current = index;
+
+    // every import can only appear once in a valid chain
+    for _ in 0..=asset.imports.len() {
+        let import = match current.is_import() {
+            true => asset.get_import(current),
+            false => None,
+        };
+        let import = match import {
+            Some(import) => import,
+            None => break,
+        };
+        chain.push(format!(
+            "{} ({})",
+            import.object_name.content, import.class_name.content
+        ));
+
+        if import.outer_index.index == 0 {
+            if import.class_name.content == "Package" {
+                return Ok(());
+            }
+            break;
+        }
+        current = import.outer_index;
+    }
+
+    Err(IntegratorError::BrokenImportChain { chain }.into())
+}
+
+/// Collect the soft object paths held by a property and the properties nested in it
+fn soft_object_names<'a>(property: &'a Property, names: &mut Vec<&'a str>) {
+    if let Some(soft_object_property) = cast!(Property, SoftObjectProperty, property) {
+        names.push(&soft_object_property.value.content);
+    } else if let Some(array_property) = cast!(Property, ArrayProperty, property) {
+        for element in &array_property.value {
+            soft_object_names(element, names);
+        }
+    } else if let Some(struct_property) = cast!(Property, StructProperty, property) {
+        for field in &struct_property.value {
+            soft_object_names(field, names);
+        }
+    }
+}
+
+/// Check that every soft object path in the properties at `locations`,
+/// as `(export index, property index)`, is in the asset's name map.
+///
+/// The paths are read back from the properties, so a property built from another string
+/// than the one registered is caught before the asset breaks in game.
+pub(crate) fn verify_soft_object_names(
+    asset: &Asset,
+    asset_name: &str,
+    locations: &[(usize, usize)],
+) -> Result<(), io::Error> {
+    for (export_index, property_index) in locations {
+        let property = match asset
+            .exports
+            .get(*export_index)
+            .and_then(|e| e.get_normal_export())
+            .and_then(|e| e.properties.get(*property_index))
+        {
+            Some(property) => property,
+            None => continue,
+        };
+
+        let mut names = Vec::new();
+        soft_object_names(property, &mut names);
+        if let Some(name) = names
+            .into_iter()
+            .find(|e| asset.search_name_reference(&e.to_string()).is_none())
+        {
+            return Err(IntegratorError::corrupted_asset(
+                asset_name,
+                &format!(
+                    "soft object path {} of {} isn't in the name map",
+                    name,
+                    property.get_name().content
+                ),
+            )
+            .into());
+        }
+    }
+    Ok(())
+}
+
+/// Turn content paths written relative to the content folder, like `Items/MyItem`
+/// or `Content/Items/MyItem`, into the canonical `/Game/Items/MyItem`.
+///
+/// Anything else is returned as is and left for `game_to_absolute` to reject.
+pub(crate) fn normalize_content_path(path: &str) -> String {
+    if GAME_REGEX.is_match(path)
+        || path.is_empty()
+        || path.starts_with('/')
+        || path.contains('\\')
+        || path.contains(':')
+    {
+        return path.to_string();
+    }
+
+    let relative = path
+        .strip_prefix("Content/")
+        .or_else(|| path.strip_prefix("Game/"))
+        .unwrap_or(path);
+    let normalized = format!("/Game/{}", relative);
+    info!(
+        "Normalized content path {} to {}, use the full path in metadata",
+        path, normalized
+    );
+    normalized
+}
+
+/// Whether players ever load a map
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum MapKind {
+    /// Maps players play on
+    Release,
+    /// Development maps that are shipped but never loaded by players
+    Test,
+    /// The tutorial, only integrated when enabled in the settings
+    Tutorial,
+}
+
+/// A map the handlers integrate into
+#[derive(Debug, Clone, Deserialize)]
+struct MapEntry {
+    path: String,
+    kind: MapKind,
+}
+
+/// Maps integrated unless overridden
+const DEFAULT_MAPS: &str = include_str!("../../assets/maps.json");
+
+lazy_static! {
+    static ref MAPS: Vec<MapEntry> = load_maps();
+}
+
+/// Read the maps to integrate from the settings' maps file, falling back to the default list
+fn load_maps() -> Vec<MapEntry> {
+    if let Some(maps_file) = &SETTINGS.maps_file {
+        let maps = fs::read_to_string(maps_file)
+            .map_err(|e| e.to_string())
+            .and_then(|e| serde_json::from_str(&e).map_err(|e| e.to_string()));
+        match maps {
+            Ok(maps) => {
+                info!("Using the maps listed in {:?}", maps_file);
+                return maps;
+            }
+            Err(e) => warn!(
+                "Ignoring invalid maps file {:?}, using the default maps: {}",
+                maps_file, e
+            ),
+        }
+    }
+    serde_json::from_str(DEFAULT_MAPS).expect("Corrupted default maps")
+}
+
+/// Paths of the maps to integrate, test maps and the tutorial only when enabled in the settings
+pub(crate) fn map_paths() -> Vec<&'static str> {
+    filter_maps(
+        &MAPS,
+        SETTINGS.include_test_maps,
+        SETTINGS.include_tutorial_map,
+    )
+}
+
+fn filter_maps(
+    maps: &[MapEntry],
+    include_test_maps: bool,
+    include_tutorial_map: bool,
+) -> Vec<&str> {
+    maps.iter()
+        .filter(|e| match e.kind {
+            MapKind::Release => true,
+            MapKind::Test => include_test_maps,
+            MapKind::Tutorial => include_tutorial_map,
+        })
+        .map(|e| e.path.as_str())
+        .collect()
+}
+
+/// Maps a handler edits, warns when there are none as nothing would be integrated
+pub(crate) fn handler_map_paths(handler_name: &str) -> Vec<&'static str> {
+    let map_paths = map_paths();
+    if map_paths.is_empty() {
+        warn!(
+            "No maps configured, {} will not be integrated",
+            handler_name
+        );
+    }
+    map_paths
+}
+
+/// Whether a map is in a mod's exclusion list, maps can be named by path or just by name
+pub(crate) fn is_excluded_map(map_path: &str, exclude_maps: &[String]) -> bool {
+    let map_name = Path::new(map_path).file_stem();
+    exclude_maps
+        .iter()
+        .any(|e| Path::new(e).file_stem() == map_name)
+}
+
+/// Whether a map is a development map
+pub(crate) fn is_test_map(map_path: &str) -> bool {
+    MAPS.iter()
+        .any(|e| e.path == map_path && e.kind == MapKind::Test)
+}
+
+/// Serialize an asset and parse what was written, as the game would read it
+fn write_and_read_back(asset: &Asset, name: &str) -> Result<Asset, io::Error> {
+    let mut asset_data = Cursor::new(Vec::new());
+    let mut export_data = Cursor::new(Vec::new());
+    asset
+        .write_data(&mut asset_data, Some(&mut export_data))
+        .map_err(|e| IntegratorError::asset_data(name, e))?;
+
+    let mut written = Asset::new(asset_data.into_inner(), Some(export_data.into_inner()));
+    written.engine_version = asset.engine_version;
+    written
+        .parse_data()
+        .map_err(|e| IntegratorError::asset_data(name, e))?;
+    Ok(written)
+}
+
+/// Serialize an asset and read it back, warning if its package guid or flags changed on the way.
+///
+/// Handlers never edit those, so a difference means the serialization path corrupted the asset.
+fn verify_package_header(asset: &Asset, name: &str) -> Result<(), io::Error> {
+    let written = write_and_read_back(asset, name)?;
+    if written.package_guid != asset.package_guid {
+        warn!(
+            "Package guid of {} changed when writing it, {:?} became {:?}",
+            name, asset.package_guid, written.package_guid
+        );
+    }
+    if written.package_flags != asset.package_flags {
+        warn!(
+            "Package flags of {} changed when writing it, {:?} became {:?}",
+            name, asset.package_flags, written.package_flags
+        );
+    }
+    Ok(())
+}
+
+/// Names held by a property: its name, its type names and name values,
+/// and those of the properties nested in it
+fn property_names(property: &Property, names: &mut HashSet<String>) {
+    names.insert(property.get_name().content);
+    names.insert(property.to_serialized_name());
+
+    if let Some(soft_object_property) = cast!(Property, SoftObjectProperty, property) {
+        names.insert(soft_object_property.value.content.clone());
+    } else if let Some(name_property) = cast!(Property, NameProperty, property) {
+        names.insert(name_property.value.content.clone());
+    } else if let Some(array_property) = cast!(Property, ArrayProperty, property) {
+        names.extend(
+            array_property
+                .array_type
+                .as_ref()
+                .map(|e| e.content.clone()),
+        );
+        for element in &array_property.value {
+            property_names(element, names);
+        }
+    } else if let Some(struct_property) = cast!(Property, StructProperty, property) {
+        names.extend(
+            struct_property
+                .struct_type
+                .as_ref()
+                .map(|e| e.content.clone()),
+        );
+        for field in &struct_property.value {
+            property_names(field, names);
+        }
+    }
+}
+
+/// Names the imports and exports of an asset hold, with the properties of every export
+fn referenced_names(imports: &[Import], exports: &[Export]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for import in imports {
+        names.insert(import.class_package.content.clone());
+        names.insert(import.class_name.content.clone());
+        names.insert(import.object_name.content.clone());
+    }
+    for export in exports {
+        names.insert(export.get_base_export().object_name.content.clone());
+        if let Some(normal_export) = export.get_normal_export() {
+            for property in &normal_export.properties {
+                property_names(property, &mut names);
+            }
+        }
+    }
+    names
+}
+
+/// Name of the package an asset like `Astro/Content/Maps/Map.umap` is, `/Game/Maps/Map`
+fn package_name(asset_name: &str) -> Option<String> {
+    let (_, content_path) = asset_name.split_once("/Content/")?;
+    let content_path = content_path
+        .rsplit_once('.')
+        .map(|(e, _)| e)
+        .unwrap_or(content_path);
+    Some(format!("/Game/{}", content_path))
+}
+
+/// Names in an asset's name map that no import, export or property references.
+///
+/// Names only used by export data the asset reader doesn't parse look orphaned too,
+/// which is why they are only reported and never removed.
+fn orphaned_names(asset: &Asset, name: &str) -> Vec<String> {
+    let mut referenced = referenced_names(&asset.imports, &asset.exports);
+    // None ends every property list and the package's own name is in its header
+    referenced.insert(String::from("None"));
+    referenced.extend(package_name(name));
+
+    asset
+        .get_name_map_index_list()
+        .iter()
+        .filter(|e| !referenced.contains(*e))
+        .cloned()
+        .collect()
+}
+
+/// [`write_asset`] with the checks enabled in the settings
+#[allow(clippy::ptr_arg)]
+pub(crate) fn write_integrated_asset(
+    integrated_pak: &mut PakFile,
+    asset: &Asset,
+    name: &String,
+) -> Result<(), io::Error> {
+    if SETTINGS.verify_writes {
+        verify_package_header(asset, name)?;
+    }
+    if SETTINGS.report_orphaned_names {
+        let orphaned = orphaned_names(asset, name);
+        if !orphaned.is_empty() {
+            warn!(
+                "{} has {} names nothing references: {}",
+                name,
+                orphaned.len(),
+                orphaned.join(", ")
+            );
+        }
+    }
+
+    write_asset(integrated_pak, asset, name).map_err(|e| IntegratorError::pak_io(name, e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use serde_json::json;
+
+    use unreal_modloader::unreal_asset::{
+        properties::{
+            object_property::SoftObjectProperty, str_property::NameProperty,
+            struct_property::StructProperty, Property,
+        },
+        unreal_types::{FName, PackageIndex},
+        Asset, Import,
+    };
+
+    use super::{
+        expect_array, expect_object, filter_maps, normalize_content_path, package_name,
+        property_names, referenced_names, soft_object_names, write_and_read_back, MapEntry,
+        MapKind, DEFAULT_MAPS,
+    };
+    use crate::assets::{ACTOR_TEMPLATE_ASSET, ACTOR_TEMPLATE_EXPORT, TEMPLATE_ENGINE_VERSION};
+
+    #[test]
+    fn writing_keeps_the_package_header() {
+        let mut asset = Asset::new(
+            ACTOR_TEMPLATE_ASSET.to_vec(),
+            Some(ACTOR_TEMPLATE_EXPORT.to_vec()),
+        );
+        asset.engine_version = TEMPLATE_ENGINE_VERSION;
+        asset.parse_data().unwrap();
+
+        let written = write_and_read_back(&asset, "ActorTemplate").unwrap();
+        assert_eq!(written.package_guid, asset.package_guid);
+        assert_eq!(written.package_flags, asset.package_flags);
+        assert_eq!(written.exports.len(), asset.exports.len());
+    }
+
+    #[test]
+    fn import_names_are_referenced() {
+        let import = Import {
+            class_package: FName::from_slice("/Script/Engine"),
+            class_name: FName::from_slice("BlueprintGeneratedClass"),
+            outer_index: PackageIndex::new(-1),
+            object_name: FName::from_slice("Component_C"),
+        };
+
+        let names = referenced_names(&[import], &[]);
+        for name in ["/Script/Engine", "BlueprintGeneratedClass", "Component_C"] {
+            assert!(names.contains(name), "{}", name);
+        }
+        // names are matched whole, not as substrings
+        assert!(!names.contains("Component"));
+        assert_eq!(names.len(), 3);
+    }
+
+    #[test]
+    fn property_names_include_types_and_values() {
+        let property: Property = StructProperty {
+            name: FName::from_slice("Entry"),
+            struct_type: Some(FName::from_slice("ItemEntry")),
+            struct_guid: Some([0u8; 16]),
+            property_guid: None,
+            duplication_index: 0,
+            serialize_none: true,
+            value: Vec::from([NameProperty {
+                name: FName::from_slice("InternalVariableName"),
+                property_guid: None,
+                duplication_index: 0,
+                value: FName::from_slice("Component"),
+            }
+            .into()]),
+        }
+        .into();
+
+        let mut names = HashSet::new();
+        property_names(&property, &mut names);
+        for name in [
+            "Entry",
+            "StructProperty",
+            "ItemEntry",
+            "InternalVariableName",
+            "NameProperty",
+            "Component",
+        ] {
+            assert!(names.contains(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn package_names_are_content_paths() {
+        assert_eq!(
+            package_name("Astro/Content/Maps/Staging_T2.umap").as_deref(),
+            Some("/Game/Maps/Staging_T2")
+        );
+        assert_eq!(package_name("Astro/Maps/Staging_T2.umap"), None);
+    }
+
+    #[test]
+    fn soft_object_names_are_read_from_nested_properties() {
+        let soft_object = |path: &str| SoftObjectProperty {
+            name: FName::from_slice("Item"),
+            property_guid: None,
+            duplication_index: 0,
+            value: FName::from_slice(path),
+            id: 0,
+        };
+        let property: Property = StructProperty {
+            name: FName::from_slice("Entry"),
+            struct_type: Some(FName::from_slice("ItemEntry")),
+            struct_guid: Some([0u8; 16]),
+            property_guid: None,
+            duplication_index: 0,
+            serialize_none: true,
+            value: Vec::from([
+                soft_object("/Game/Items/Iron.Iron").into(),
+                soft_object("/Game/Items/Copper.Copper").into(),
+            ]),
+        }
+        .into();
+
+        let mut names = Vec::new();
+        soft_object_names(&property, &mut names);
+        assert_eq!(
+            names,
+            ["/Game/Items/Iron.Iron", "/Game/Items/Copper.Copper"]
+        );
+    }
+
+    #[test]
+    fn relative_content_paths_are_normalized() {
+        for path in [
+            "Items/MyItem",
+            "Content/Items/MyItem",
+            "Game/Items/MyItem",
+            "/Game/Items/MyItem",
+        ] {
+            assert_eq!(
+                normalize_content_path(path),
+                "/Game/Items/MyItem",
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn other_paths_are_left_alone() {
+        for path in [
+            "",
+            "/Engine/Items/MyItem",
+            "Items\\MyItem",
+            "C:/Items/MyItem",
+        ] {
+            assert_eq!(normalize_content_path(path), path);
+        }
+    }
+
+    #[test]
+    fn metadata_of_the_expected_type_is_kept() {
+        let array = json!(["/Game/Items/MyItem"]);
+        let object = json!({ "/Game/Items/MyItem": [] });
+        assert_eq!(expect_array("handler", 0, &array), array.as_array());
+        assert_eq!(expect_object("handler", 0, &object), object.as_object());
+    }
+
+    #[test]
+    fn metadata_of_the_wrong_type_is_skipped() {
+        for value in [
+            json!(null),
+            json!(true),
+            json!(1),
+            json!("/Game/Items/MyItem"),
+        ] {
+            assert_eq!(expect_array("handler", 0, &value), None);
+            assert_eq!(expect_object("handler", 0, &value), None);
+        }
+        assert_eq!(expect_array("handler", 0, &json!({})), None);
+        assert_eq!(expect_object("handler", 0, &json!([])), None);
+    }
+
+    fn map(path: &str, kind: MapKind) -> MapEntry {
+        MapEntry {
+            path: path.to_string(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn default_maps_include_release_maps() {
+        let maps: Vec<MapEntry> = serde_json::from_str(DEFAULT_MAPS).unwrap();
+        assert!(!filter_maps(&maps, false, false).is_empty());
+    }
+
+    #[test]
+    fn maps_are_filtered_by_kind() {
+        let maps = [
+            map("/Game/Maps/Release", MapKind::Release),
+            map("/Game/Maps/Test", MapKind::Test),
+            map("/Game/Maps/Tutorial", MapKind::Tutorial),
+        ];
+        assert_eq!(filter_maps(&maps, false, false), ["/Game/Maps/Release"]);
+        assert_eq!(
+            filter_maps(&maps, true, true),
+            [
+                "/Game/Maps/Release",
+                "/Game/Maps/Test",
+                "/Game/Maps/Tutorial"
+            ]
+        );
+    }
+
+    #[test]
+    fn only_test_maps_leave_nothing_to_integrate() {
+        let maps = [map("/Game/Maps/Test", MapKind::Test)];
+        assert!(filter_maps(&maps, false, false).is_empty());
+    }
+}
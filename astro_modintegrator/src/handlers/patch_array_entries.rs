@@ -0,0 +1,386 @@
+use std::{collections::HashMap, io};
+
+use unreal_modloader::unreal_asset::{
+    cast,
+    exports::ExportNormalTrait,
+    properties::{
+        object_property::{ObjectProperty, SoftObjectProperty},
+        str_property::{NameProperty, StrProperty},
+        struct_property::StructProperty,
+        Property, PropertyDataTrait,
+    },
+    reader::asset_trait::AssetTrait,
+    unreal_types::FName,
+    Asset,
+};
+use unreal_modloader::unreal_modintegrator::{
+    helpers::{find_asset, game_to_absolute, get_asset, read_asset},
+    IntegratorConfig,
+};
+use unreal_modloader::unreal_pak::PakFile;
+
+use super::item_list_entries::{add_class_import, find_arrays, set_struct_field, split_item_path};
+use super::report::{Change, HandlerReport};
+use super::timing::{AssetPhase, AssetTimings};
+use super::{
+    expect_object, normalize_content_path, verify_soft_object_names, write_integrated_asset,
+};
+use crate::{
+    assets::asset_engine_version, error::IntegratorError, schema::validate_metadata,
+    AstroIntegratorConfig,
+};
+
+/// How a patch picks the array entry it replaces
+#[derive(Debug)]
+enum EntrySelector {
+    /// Position in the array as the game or mod ships it
+    Index(usize),
+    /// Item path, or the value itself for string and name arrays
+    Match(String),
+}
+
+/// A replacement for a single array entry
+#[derive(Debug)]
+struct ArrayPatch {
+    mod_index: usize,
+    selector: EntrySelector,
+    value: serde_json::Value,
+}
+
+/// Patches of every mod, keyed by asset path and then by array name
+type ArrayPatches = HashMap<String, HashMap<String, Vec<ArrayPatch>>>;
+
+fn parse_patch(
+    mod_index: usize,
+    field: &str,
+    patch: &serde_json::Value,
+) -> Result<ArrayPatch, io::Error> {
+    let invalid_patch = || IntegratorError::invalid_metadata("patch_array_entries", field);
+
+    let selector = match (patch.get("index"), patch.get("match")) {
+        (Some(index), None) => EntrySelector::Index(
+            index
+                .as_u64()
+                .and_then(|e| usize::try_from(e).ok())
+                .ok_or_else(invalid_patch)?,
+        ),
+        (None, Some(entry)) => {
+            EntrySelector::Match(entry.as_str().ok_or_else(invalid_patch)?.to_string())
+        }
+        _ => return Err(invalid_patch().into()),
+    };
+    let value = patch.get("value").cloned().ok_or_else(invalid_patch)?;
+
+    Ok(ArrayPatch {
+        mod_index,
+        selector,
+        value,
+    })
+}
+
+/// Read an asset as the mod overriding it or the game ships it, without the edits of
+/// handlers that already ran
+#[allow(clippy::ptr_arg)]
+fn read_original_asset(
+    game_paks: &mut Vec<PakFile>,
+    mod_paks: &mut Vec<PakFile>,
+    asset_name: &String,
+) -> Result<Asset, io::Error> {
+    let (paks, index) = match find_asset(mod_paks, asset_name) {
+        Some(index) => (mod_paks, index),
+        None => match find_asset(game_paks, asset_name) {
+            Some(index) => (game_paks, index),
+            None => {
+                return Err(IntegratorError::AssetNotFound {
+                    path: asset_name.clone(),
+                    referenced_by: String::from("Patched asset"),
+                    source: io::Error::from(io::ErrorKind::NotFound),
+                }
+                .into())
+            }
+        },
+    };
+    read_asset(
+        &mut paks[index],
+        asset_engine_version(asset_name),
+        asset_name,
+    )
+    .map_err(|e| IntegratorError::pak_io(asset_name, e).into())
+}
+
+/// Length of each array in the original asset by its export and property index.
+///
+/// Handlers only ever append exports and array entries,
+/// so the indices still point at the same arrays in the integrated asset.
+fn original_array_lengths(original: &Asset, array_name: &str) -> HashMap<(usize, usize), usize> {
+    find_arrays(original, array_name)
+        .into_iter()
+        .map(|(i, j)| {
+            let len = original.exports[i]
+                .get_normal_export()
+                .and_then(|e| cast!(Property, ArrayProperty, &e.properties[j]))
+                .map(|e| e.value.len())
+                .unwrap_or(0);
+            ((i, j), len)
+        })
+        .collect()
+}
+
+/// Whether an array entry is the one a match refers to
+fn entry_matches(asset: &Asset, entry: &Property, selector: &str) -> Result<bool, io::Error> {
+    if let Some(object_property) = cast!(Property, ObjectProperty, entry) {
+        let (real_name, class_name, _) = split_item_path(&normalize_content_path(selector))?;
+        let import = match asset.get_import(object_property.value) {
+            Some(import) => import,
+            None => return Ok(false),
+        };
+        let package_name = asset
+            .get_import(import.outer_index)
+            .map(|e| e.object_name.content.clone());
+        Ok(import.object_name.content == class_name && package_name.as_deref() == Some(&real_name))
+    } else if let Some(soft_object_property) = cast!(Property, SoftObjectProperty, entry) {
+        let (real_name, _, soft_class_name) = split_item_path(&normalize_content_path(selector))?;
+        Ok(soft_object_property.value.content == real_name + "." + &soft_class_name)
+    } else if let Some(str_property) = cast!(Property, StrProperty, entry) {
+        Ok(str_property.value.as_deref() == Some(selector))
+    } else if let Some(name_property) = cast!(Property, NameProperty, entry) {
+        Ok(name_property.value.content == selector)
+    } else {
+        // only entries holding a single value can be matched, others need an index
+        Err(IntegratorError::unsupported_property(entry, &entry.get_name().content).into())
+    }
+}
+
+/// Build the entry replacing `entry`, of the same type.
+///
+/// Struct entries only change the sub-properties given in `value`.
+fn build_replacement(
+    asset: &mut Asset,
+    name: FName,
+    entry: &Property,
+    value: &serde_json::Value,
+) -> Result<Property, io::Error> {
+    let entry_name = name.content.clone();
+    let invalid_value = || {
+        IntegratorError::invalid_value(&entry_name, &format!("unexpected replacement {}", value))
+    };
+
+    if let Some(struct_property) = cast!(Property, StructProperty, entry) {
+        let fields = value.as_object().ok_or_else(invalid_value)?;
+        let mut replacement = struct_property.clone();
+        for (field_name, field_value) in fields {
+            let property = replacement
+                .value
+                .iter_mut()
+                .find(|e| &e.get_name().content == field_name)
+                .ok_or_else(|| {
+                    IntegratorError::invalid_value(
+                        &entry_name,
+                        &format!("unknown struct field {}", field_name),
+                    )
+                })?;
+            set_struct_field(asset, property, field_value)?;
+        }
+        return Ok(replacement.into());
+    }
+
+    let value = value.as_str().ok_or_else(invalid_value)?;
+    if cast!(Property, ObjectProperty, entry).is_some() {
+        let (real_name, class_name, _) = split_item_path(&normalize_content_path(value))?;
+        Ok(ObjectProperty {
+            name,
+            property_guid: None,
+            duplication_index: 0,
+            value: add_class_import(asset, &real_name, &class_name)?,
+        }
+        .into())
+    } else if cast!(Property, SoftObjectProperty, entry).is_some() {
+        let (real_name, _, soft_class_name) = split_item_path(&normalize_content_path(value))?;
+        let soft_object_path = real_name.clone() + "." + &soft_class_name;
+        asset.add_name_reference(real_name, false);
+        asset.add_name_reference(soft_object_path.clone(), false);
+        Ok(SoftObjectProperty {
+            name,
+            property_guid: None,
+            duplication_index: 0,
+            value: FName::new(soft_object_path, 0),
+            id: 0,
+        }
+        .into())
+    } else if cast!(Property, StrProperty, entry).is_some() {
+        Ok(StrProperty {
+            name,
+            property_guid: None,
+            duplication_index: 0,
+            value: Some(value.to_string()),
+        }
+        .into())
+    } else if cast!(Property, NameProperty, entry).is_some() {
+        Ok(NameProperty {
+            name,
+            property_guid: None,
+            duplication_index: 0,
+            value: asset.add_fname(value),
+        }
+        .into())
+    } else {
+        Err(IntegratorError::unsupported_property(entry, &entry_name).into())
+    }
+}
+
+/// Replace the selected entry of an array, erroring if the entry doesn't exist.
+///
+/// Only the first `original_len` entries, the ones the array had before any handler ran,
+/// can be patched. Handlers run in no particular order, so entries other handlers append
+/// may or may not be there yet.
+fn apply_patch(
+    asset: &mut Asset,
+    asset_name: &str,
+    array_name: &str,
+    (export_index, property_index): (usize, usize),
+    original_len: usize,
+    patch: &ArrayPatch,
+) -> Result<(), io::Error> {
+    let array_property = asset.exports[export_index]
+        .get_normal_export()
+        .and_then(|e| cast!(Property, ArrayProperty, &e.properties[property_index]))
+        .expect("Corrupted memory");
+    let original_len = original_len.min(array_property.value.len());
+
+    let position = match &patch.selector {
+        EntrySelector::Index(index) => (*index < original_len).then_some(*index),
+        EntrySelector::Match(selector) => {
+            let mut position = None;
+            for (i, entry) in array_property.value.iter().take(original_len).enumerate() {
+                if entry_matches(asset, entry, selector)? {
+                    position = Some(i);
+                    break;
+                }
+            }
+            position
+        }
+    };
+    let position = position.ok_or_else(|| {
+        IntegratorError::missing_export(
+            asset_name,
+            &format!(
+                "entry {:?} in {} ({} original entries)",
+                patch.selector, array_name, original_len
+            ),
+        )
+    })?;
+
+    let name = array_property.name.clone();
+    let entry = array_property.value[position].clone();
+    let replacement = build_replacement(asset, name, &entry, &patch.value)?;
+
+    let array_property = asset.exports[export_index]
+        .get_normal_export_mut()
+        .and_then(|e| cast!(Property, ArrayProperty, &mut e.properties[property_index]))
+        .expect("Corrupted memory");
+    array_property.value[position] = replacement;
+    Ok(())
+}
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_patch_array_entries(
+    _data: &(),
+    integrated_pak: &mut PakFile,
+    game_paks: &mut Vec<PakFile>,
+    mod_paks: &mut Vec<PakFile>,
+    patch_array_entries_maps: &Vec<serde_json::Value>,
+) -> Result<(), io::Error> {
+    validate_metadata("patch_array_entries", patch_array_entries_maps)?;
+
+    let mut patches: ArrayPatches = HashMap::new();
+    for (mod_index, patch_array_entries_map) in patch_array_entries_maps.iter().enumerate() {
+        let patch_array_entries_map =
+            match expect_object("patch_array_entries", mod_index, patch_array_entries_map) {
+                Some(patch_array_entries_map) => patch_array_entries_map,
+                None => continue,
+            };
+
+        for (asset_name, arrays) in patch_array_entries_map {
+            let arrays = arrays.as_object().ok_or_else(|| {
+                IntegratorError::invalid_metadata("patch_array_entries", asset_name)
+            })?;
+            let asset_patches = patches.entry(asset_name.clone()).or_default();
+
+            for (array_name, array_patches) in arrays {
+                let field = format!("{}.{}", asset_name, array_name);
+                let array_patches = array_patches.as_array().ok_or_else(|| {
+                    IntegratorError::invalid_metadata("patch_array_entries", &field)
+                })?;
+                for patch in array_patches {
+                    asset_patches
+                        .entry(array_name.clone())
+                        .or_default()
+                        .push(parse_patch(mod_index, &field, patch)?);
+                }
+            }
+        }
+    }
+
+    let mut timings = AssetTimings::new("patch_array_entries");
+    let mut report = HandlerReport::new("patch_array_entries");
+    for (asset_name, arrays) in &patches {
+        let asset_name = game_to_absolute(
+            AstroIntegratorConfig::GAME_NAME,
+            &normalize_content_path(asset_name),
+        )
+        .ok_or_else(|| IntegratorError::invalid_metadata("patch_array_entries", asset_name))?;
+        let timer = timings.start();
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            asset_engine_version(&asset_name),
+        )?;
+        let original = read_original_asset(game_paks, mod_paks, &asset_name)?;
+        timings.record(&asset_name, AssetPhase::Read, timer);
+        let timer = timings.start();
+
+        for (array_name, array_patches) in arrays {
+            let array_locations = find_arrays(&asset, array_name);
+            if array_locations.is_empty() {
+                return Err(IntegratorError::missing_export(
+                    &asset_name,
+                    &format!("array {}", array_name),
+                )
+                .into());
+            }
+
+            let original_lengths = original_array_lengths(&original, array_name);
+            for patch in array_patches {
+                for location in &array_locations {
+                    apply_patch(
+                        &mut asset,
+                        &asset_name,
+                        array_name,
+                        *location,
+                        original_lengths.get(location).copied().unwrap_or(0),
+                        patch,
+                    )?;
+                }
+                report.applied(
+                    patch.mod_index,
+                    &asset_name,
+                    Change::Replaced,
+                    &format!("entry {:?} of {}", patch.selector, array_name),
+                );
+            }
+            verify_soft_object_names(&asset, &asset_name, &array_locations)?;
+        }
+
+        timings.record(&asset_name, AssetPhase::Modify, timer);
+
+        let timer = timings.start();
+        write_integrated_asset(integrated_pak, &asset, &asset_name)?;
+        timings.record(&asset_name, AssetPhase::Write, timer);
+    }
+
+    timings.finish();
+    report.log();
+    Ok(())
+}
@@ -0,0 +1,139 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display},
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::{info, warn};
+
+use crate::settings::SETTINGS;
+
+/// Readable log of every change the handlers made, written when enabled in the settings
+pub const CHANGE_LOG_FILE_NAME: &str = "integration_changes.txt";
+
+/// Where the change log goes, next to the integrated pak when the loader says where that is
+pub fn change_log_path() -> PathBuf {
+    match &SETTINGS.change_log_dir {
+        Some(change_log_dir) => change_log_dir.join(CHANGE_LOG_FILE_NAME),
+        None => PathBuf::from(CHANGE_LOG_FILE_NAME),
+    }
+}
+
+/// Empty the change log for a new integration, handlers append their changes after this
+pub(crate) fn start_change_log() {
+    if !SETTINGS.change_log {
+        return;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|e| e.as_secs())
+        .unwrap_or(0);
+    let path = change_log_path();
+    if let Err(e) = File::create(&path)
+        .and_then(|mut file| writeln!(file, "Integration started at {}\n", timestamp))
+    {
+        warn!("Failed to write {:?}: {}", path, e);
+    }
+}
+
+/// What a handler did with an entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Change {
+    /// The entry is new
+    Added,
+    /// The entry took the place of an existing one
+    Replaced,
+    /// The entry's fields were written into an existing one
+    Merged,
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Added => write!(f, "added"),
+            Change::Replaced => write!(f, "replaced"),
+            Change::Merged => write!(f, "merged into"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ModCounts {
+    applied: usize,
+    skipped: usize,
+}
+
+/// How many entries of each mod a handler applied or had to skip.
+///
+/// Handlers only get the metadata of every mod in load order,
+/// so mods are identified by their position in that list.
+/// Handlers editing every map count an entry once per map.
+#[derive(Debug)]
+pub(crate) struct HandlerReport {
+    handler_name: &'static str,
+    mods: BTreeMap<usize, ModCounts>,
+    /// Every applied entry, only collected when the change log is enabled
+    changes: Vec<String>,
+}
+
+impl HandlerReport {
+    pub fn new(handler_name: &'static str) -> Self {
+        HandlerReport {
+            handler_name,
+            mods: BTreeMap::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    pub fn applied(&mut self, mod_index: usize, asset: &str, change: Change, entry: &str) {
+        self.mods.entry(mod_index).or_default().applied += 1;
+        if SETTINGS.change_log {
+            self.changes.push(format!(
+                "mod #{}: {}: {} {}",
+                mod_index + 1,
+                asset,
+                change,
+                entry
+            ));
+        }
+    }
+
+    pub fn skipped(&mut self, mod_index: usize) {
+        self.mods.entry(mod_index).or_default().skipped += 1;
+    }
+
+    pub fn log(&self) {
+        for (mod_index, counts) in &self.mods {
+            info!(
+                "{}: mod #{} {} entries applied, {} skipped",
+                self.handler_name,
+                mod_index + 1,
+                counts.applied,
+                counts.skipped
+            );
+        }
+
+        if SETTINGS.change_log && !self.changes.is_empty() {
+            if let Err(e) = self.write_change_log() {
+                warn!("Failed to write {:?}: {}", change_log_path(), e);
+            }
+        }
+    }
+
+    /// Append this handler's changes to the change log, so mod authors can check them in game
+    fn write_change_log(&self) -> Result<(), io::Error> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(change_log_path())?;
+        writeln!(file, "{}", self.handler_name)?;
+        for change in &self.changes {
+            writeln!(file, "  {}", change)?;
+        }
+        writeln!(file)
+    }
+}
@@ -0,0 +1,109 @@
+use std::{collections::HashMap, io};
+
+use log::{info, warn};
+use unreal_modloader::unreal_asset::{
+    cast,
+    exports::{string_table_export::StringTableExport, Export},
+};
+use unreal_modloader::unreal_modintegrator::{
+    helpers::{game_to_absolute, get_asset},
+    IntegratorConfig,
+};
+use unreal_modloader::unreal_pak::PakFile;
+
+use super::report::{Change, HandlerReport};
+use super::timing::{AssetPhase, AssetTimings};
+use super::{expect_object, normalize_content_path, write_integrated_asset};
+use crate::{
+    assets::asset_engine_version, error::IntegratorError, schema::validate_metadata,
+    AstroIntegratorConfig,
+};
+
+#[allow(clippy::ptr_arg)]
+pub(crate) fn handle_string_table_entries(
+    _data: &(),
+    integrated_pak: &mut PakFile,
+    game_paks: &mut Vec<PakFile>,
+    mod_paks: &mut Vec<PakFile>,
+    string_table_entries_maps: &Vec<serde_json::Value>,
+) -> Result<(), io::Error> {
+    validate_metadata("string_table_entries", string_table_entries_maps)?;
+
+    // (mod index, key, value) per string table, in mod load order so later mods win
+    let mut new_entries: HashMap<String, Vec<(usize, String, String)>> = HashMap::new();
+    for (mod_index, string_table_entries_map) in string_table_entries_maps.iter().enumerate() {
+        let string_table_entries_map =
+            match expect_object("string_table_entries", mod_index, string_table_entries_map) {
+                Some(string_table_entries_map) => string_table_entries_map,
+                None => continue,
+            };
+
+        for (asset_name, entries) in string_table_entries_map {
+            let entries = entries.as_object().ok_or_else(|| {
+                IntegratorError::invalid_metadata("string_table_entries", asset_name)
+            })?;
+            let asset_entries = new_entries.entry(asset_name.clone()).or_default();
+
+            for (key, value) in entries {
+                let value = value.as_str().ok_or_else(|| {
+                    IntegratorError::invalid_metadata(
+                        "string_table_entries",
+                        &format!("{}.{}", asset_name, key),
+                    )
+                })?;
+                asset_entries.push((mod_index, key.clone(), value.to_string()));
+            }
+        }
+    }
+
+    let mut timings = AssetTimings::new("string_table_entries");
+    let mut report = HandlerReport::new("string_table_entries");
+    for (asset_name, entries) in &new_entries {
+        let asset_name = game_to_absolute(
+            AstroIntegratorConfig::GAME_NAME,
+            &normalize_content_path(asset_name),
+        )
+        .ok_or_else(|| IntegratorError::invalid_metadata("string_table_entries", asset_name))?;
+        let timer = timings.start();
+        let mut asset = get_asset(
+            integrated_pak,
+            game_paks,
+            mod_paks,
+            &asset_name,
+            asset_engine_version(&asset_name),
+        )?;
+        timings.record(&asset_name, AssetPhase::Read, timer);
+        let timer = timings.start();
+
+        let string_table = asset
+            .exports
+            .iter_mut()
+            .find_map(|e| cast!(Export, StringTableExport, e))
+            .ok_or_else(|| IntegratorError::missing_export(&asset_name, "StringTable export"))?;
+
+        info!("Adding {} entries to {}", entries.len(), asset_name);
+        for (mod_index, key, value) in entries {
+            let change = match string_table.table.value.insert(key.clone(), value.clone()) {
+                Some(previous) => {
+                    warn!(
+                        "Replacing {} in {}, {:?} is now {:?}",
+                        key, asset_name, previous, value
+                    );
+                    Change::Replaced
+                }
+                None => Change::Added,
+            };
+            report.applied(*mod_index, &asset_name, change, &format!("key {}", key));
+        }
+
+        timings.record(&asset_name, AssetPhase::Modify, timer);
+
+        let timer = timings.start();
+        write_integrated_asset(integrated_pak, &asset, &asset_name)?;
+        timings.record(&asset_name, AssetPhase::Write, timer);
+    }
+
+    timings.finish();
+    report.log();
+    Ok(())
+}
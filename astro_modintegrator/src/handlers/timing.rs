@@ -0,0 +1,153 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use log::{debug, info};
+
+use crate::settings::SETTINGS;
+
+/// How many of the slowest assets get listed in the integration summary
+const SUMMARY_LENGTH: usize = 5;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum AssetPhase {
+    Read,
+    Modify,
+    Write,
+}
+
+#[derive(Debug, Default)]
+struct AssetTiming {
+    handler_name: &'static str,
+    asset_name: String,
+    read: Duration,
+    modify: Duration,
+    write: Duration,
+}
+
+impl AssetTiming {
+    fn total(&self) -> Duration {
+        self.read + self.modify + self.write
+    }
+}
+
+/// Time spent in every handler that finished, for the summary once integration is done
+#[derive(Debug, Default)]
+struct CollectedTimings {
+    handlers: Vec<(&'static str, Duration)>,
+    assets: Vec<AssetTiming>,
+}
+
+lazy_static! {
+    static ref COLLECTED_TIMINGS: Mutex<CollectedTimings> = Mutex::new(CollectedTimings::default());
+}
+
+/// Per-asset timing of a single handler run.
+///
+/// Timings are only collected when enabled in the settings,
+/// otherwise every call is a no-op.
+#[derive(Debug)]
+pub(crate) struct AssetTimings {
+    handler_name: &'static str,
+    enabled: bool,
+    started: Option<Instant>,
+    assets: Vec<AssetTiming>,
+}
+
+impl AssetTimings {
+    pub fn new(handler_name: &'static str) -> Self {
+        let enabled = SETTINGS.timings;
+        AssetTimings {
+            handler_name,
+            enabled,
+            started: enabled.then(Instant::now),
+            assets: Vec::new(),
+        }
+    }
+
+    /// Start timing a phase, pass the result to [`AssetTimings::record`] once the phase is done
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    pub fn record(&mut self, asset_name: &str, phase: AssetPhase, started: Option<Instant>) {
+        let elapsed = match started {
+            Some(started) => started.elapsed(),
+            None => return,
+        };
+
+        debug!(
+            "{}: {:?} {} took {:?}",
+            self.handler_name, phase, asset_name, elapsed
+        );
+
+        let index = match self.assets.iter().position(|e| e.asset_name == asset_name) {
+            Some(index) => index,
+            None => {
+                self.assets.push(AssetTiming {
+                    handler_name: self.handler_name,
+                    asset_name: asset_name.to_string(),
+                    ..Default::default()
+                });
+                self.assets.len() - 1
+            }
+        };
+
+        let timing = &mut self.assets[index];
+        match phase {
+            AssetPhase::Read => timing.read += elapsed,
+            AssetPhase::Modify => timing.modify += elapsed,
+            AssetPhase::Write => timing.write += elapsed,
+        }
+    }
+
+    /// Hand this run's timings over to the summary logged by [`log_timing_summary`]
+    pub fn finish(self) {
+        let started = match self.started {
+            Some(started) => started,
+            None => return,
+        };
+
+        let mut collected = COLLECTED_TIMINGS.lock().expect("Poisoned timings");
+        collected
+            .handlers
+            .push((self.handler_name, started.elapsed()));
+        collected.assets.extend(self.assets);
+    }
+}
+
+/// Log how long every handler took and the slowest assets over the whole integration,
+/// then start over for the next one
+pub(crate) fn log_timing_summary() {
+    let mut collected = std::mem::take(&mut *COLLECTED_TIMINGS.lock().expect("Poisoned timings"));
+    if collected.handlers.is_empty() {
+        return;
+    }
+
+    let total: Duration = collected.handlers.iter().map(|(_, e)| *e).sum();
+    info!(
+        "Handlers took {:?} for {} assets",
+        total,
+        collected.assets.len()
+    );
+    for (handler_name, elapsed) in &collected.handlers {
+        info!("{} took {:?}", handler_name, elapsed);
+    }
+
+    collected
+        .assets
+        .sort_by_key(|e| std::cmp::Reverse(e.total()));
+    for timing in collected.assets.iter().take(SUMMARY_LENGTH) {
+        info!(
+            "Slowest: {} in {} {:?} (read {:?}, modify {:?}, write {:?})",
+            timing.asset_name,
+            timing.handler_name,
+            timing.total(),
+            timing.read,
+            timing.modify,
+            timing.write
+        );
+    }
+}
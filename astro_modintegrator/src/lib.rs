@@ -1,21 +1,33 @@
-use std::{collections::HashMap, io};
+use std::{cell::RefCell, collections::HashMap, io, rc::Rc};
 
 use crate::unreal_modintegrator::bake_instructions;
-use handlers::MAP_PATHS;
-use unreal_modloader::unreal_asset::ue4version::VER_UE4_23;
+use handlers::handler_map_paths;
 use unreal_modloader::unreal_modintegrator::helpers::game_to_absolute;
 use unreal_modloader::unreal_modintegrator::BakedInstructions;
 use unreal_modloader::unreal_modintegrator::IntegratorConfig;
 
 use lazy_static::lazy_static;
+use log::error;
 
 pub mod assets;
+pub mod compat;
+pub mod dump;
+pub mod error;
 pub(crate) mod handlers;
+pub mod plan;
+pub mod process;
+pub mod schema;
+pub(crate) mod session;
+pub mod settings;
+
+use crate::session::IntegrationSession;
 
 use crate::handlers::{
-    biome_placement_modifiers, item_list_entries, linked_actor_components, mission_trailheads,
+    biome_placement_modifiers, data_table_entries, item_list_entries, linked_actor_components,
+    mission_trailheads, patch_array_entries, string_table_entries,
 };
 
+pub use handlers::report::{change_log_path, CHANGE_LOG_FILE_NAME};
 pub use unreal_modloader;
 pub use unreal_modloader::unreal_asset;
 pub use unreal_modloader::unreal_modintegrator;
@@ -87,24 +99,80 @@ impl<'data> IntegratorConfig<'data, (), io::Error> for AstroIntegratorConfig {
             Box::new(item_list_entries::handle_item_list_entries),
         );
 
+        handlers.insert(
+            String::from("patch_array_entries"),
+            Box::new(patch_array_entries::handle_patch_array_entries),
+        );
+
+        handlers.insert(
+            String::from("data_table_entries"),
+            Box::new(data_table_entries::handle_data_table_entries),
+        );
+
+        handlers.insert(
+            String::from("string_table_entries"),
+            Box::new(string_table_entries::handle_string_table_entries),
+        );
+
         handlers.insert(
             String::from("biome_placement_modifiers"),
             Box::new(biome_placement_modifiers::handle_biome_placement_modifiers),
         );
 
+        // the integrator doesn't tell us when integration starts,
+        // so the first handler to run prepares the session
+        let session = Rc::new(RefCell::new(IntegrationSession::default()));
         handlers
+            .into_iter()
+            .map(|(name, mut handler)| {
+                let session = Rc::clone(&session);
+                let session_handler: Box<HandlerFn> = Box::new(
+                    move |data: &(),
+                          integrated_pak: &mut unreal_pak::PakFile,
+                          game_paks: &mut Vec<unreal_pak::PakFile>,
+                          mod_paks: &mut Vec<unreal_pak::PakFile>,
+                          values: &Vec<serde_json::Value>| {
+                        session.borrow_mut().prepare(game_paks, mod_paks)?;
+                        handler(data, integrated_pak, game_paks, mod_paks, values)
+                    },
+                );
+                (name, session_handler)
+            })
+            .collect()
     }
 
     fn get_instructions(&self) -> Option<BakedInstructions> {
+        if let Err(e) = assets::check_template_engine_version(assets::game_engine_version()) {
+            error!("Not integrating the notification actor: {}", e);
+            return None;
+        }
+
         let instructions = bake_instructions!(
             "persistent_actors": ["/Game/Integrator/NotificationActor"],
-            "persistent_actor_maps": MAP_PATHS
+            "persistent_actor_maps": handler_map_paths("persistent_actors")
         );
 
         Some(BakedInstructions::new(FILE_REFS.clone(), instructions))
     }
 
     const GAME_NAME: &'static str = "Astro";
-    const INTEGRATOR_VERSION: &'static str = "0.1.0";
-    const ENGINE_VERSION: i32 = VER_UE4_23;
+    const INTEGRATOR_VERSION: &'static str = env!("CARGO_PKG_VERSION");
+    // has to be known at compile time, so it's only what unreal_modintegrator's own handlers use,
+    // the handlers here use assets::game_engine_version, detected from the game's assets
+    const ENGINE_VERSION: i32 = assets::DEFAULT_ENGINE_VERSION;
+}
+
+#[cfg(test)]
+mod tests {
+    use unreal_modloader::unreal_modintegrator::IntegratorConfig;
+
+    use crate::AstroIntegratorConfig;
+
+    #[test]
+    fn integrator_version_is_the_crate_version() {
+        assert_eq!(
+            AstroIntegratorConfig::INTEGRATOR_VERSION,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
 }
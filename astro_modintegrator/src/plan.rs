@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+
+use serde_json::json;
+use unreal_modloader::unreal_modintegrator::IntegratorConfig;
+
+use crate::assets::{engine_version_name, game_engine_version, parse_engine_version};
+use crate::compat::handler_compatibility_warnings;
+use crate::handlers::{is_excluded_map, is_test_map, map_paths, normalize_content_path};
+use crate::schema::validate_integrator_metadata;
+use crate::session::mod_id;
+use crate::AstroIntegratorConfig;
+
+/// Assets a handler will edit, with the number of entries going into each
+type Targets = BTreeMap<String, usize>;
+
+/// Maps a mod's entry for a map handler must not be added to, see [`is_excluded_map`]
+fn entry_exclude_maps(entry: &serde_json::Value) -> Vec<String> {
+    entry
+        .get("exclude_maps")
+        .and_then(|e| e.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.as_str())
+        .map(String::from)
+        .collect()
+}
+
+/// Count each entry once for every map it isn't excluded from
+fn add_to_maps(targets: &mut Targets, maps: &[&str], entries: &serde_json::Value) {
+    for entry in entries.as_array().into_iter().flatten() {
+        let exclude_maps = entry_exclude_maps(entry);
+        for map_path in maps {
+            if !is_excluded_map(map_path, &exclude_maps) {
+                *targets.entry(map_path.to_string()).or_default() += 1;
+            }
+        }
+    }
+}
+
+/// Resolve the targets of one mod's entries for a handler, without reading any assets
+fn handler_targets(handler_name: &str, value: &serde_json::Value, targets: &mut Targets) {
+    match handler_name {
+        "persistent_actors" | "mission_trailheads" => add_to_maps(targets, &map_paths(), value),
+        "biome_placement_modifiers" => {
+            // the handler skips test maps, they don't have the planets' voxel volumes
+            let maps: Vec<&str> = map_paths()
+                .into_iter()
+                .filter(|e| !is_test_map(e))
+                .collect();
+            add_to_maps(targets, &maps, value)
+        }
+        "linked_actor_components" => {
+            for (actor, components) in value.as_object().into_iter().flatten() {
+                *targets.entry(normalize_content_path(actor)).or_default() +=
+                    components.as_array().map(|e| e.len()).unwrap_or(0);
+            }
+        }
+        "item_list_entries" | "patch_array_entries" => {
+            for (asset, arrays) in value.as_object().into_iter().flatten() {
+                let entries: usize = arrays
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(_, e)| e.as_array())
+                    .map(|e| e.len())
+                    .sum();
+                *targets.entry(normalize_content_path(asset)).or_default() += entries;
+            }
+        }
+        "data_table_entries" | "string_table_entries" => {
+            for (asset, entries) in value.as_object().into_iter().flatten() {
+                *targets.entry(normalize_content_path(asset)).or_default() +=
+                    entries.as_object().map(|e| e.len()).unwrap_or(0);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Mod ids grouped by a version their metadata declares, like `integrator_version`,
+/// mods that don't declare it are left out
+pub fn version_groups(mods: &[serde_json::Value], field: &str) -> BTreeMap<String, Vec<String>> {
+    let mut groups: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for metadata in mods {
+        let version = match metadata.get(field).and_then(|e| e.as_str()) {
+            Some(version) => version,
+            None => continue,
+        };
+        groups
+            .entry(version.to_string())
+            .or_default()
+            .push(mod_id(metadata).to_string());
+    }
+    groups
+}
+
+/// Describe the groupings of a version when some group doesn't agree with the running one
+fn check_version(
+    groups: BTreeMap<String, Vec<String>>,
+    field: &str,
+    running: &str,
+    agrees: impl Fn(&str) -> bool,
+) -> Option<String> {
+    if groups.keys().all(|e| agrees(e)) {
+        return None;
+    }
+
+    let groups: Vec<String> = groups
+        .iter()
+        .map(|(version, mod_ids)| format!("{}: {}", version, mod_ids.join(", ")))
+        .collect();
+    Some(format!(
+        "Mods target different {} (running: {}): {}",
+        field,
+        running,
+        groups.join("; ")
+    ))
+}
+
+/// Check that every mod targets the running integrator version and the game's engine version,
+/// which also means they agree with each other.
+///
+/// Returns a description of the groupings of every version that disagrees.
+pub fn check_mod_versions(mods: &[serde_json::Value], engine_version: i32) -> Vec<String> {
+    let integrator_version = AstroIntegratorConfig::INTEGRATOR_VERSION;
+    [
+        check_version(
+            version_groups(mods, "integrator_version"),
+            "integrator_version",
+            integrator_version,
+            |e| e.trim_start_matches('v') == integrator_version,
+        ),
+        check_version(
+            version_groups(mods, "engine_version"),
+            "engine_version",
+            engine_version_name(engine_version).unwrap_or("unknown"),
+            |e| {
+                parse_engine_version(e)
+                    .map(|e| e == engine_version)
+                    .unwrap_or(false)
+            },
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
+/// Describe what integrating the given mods would do as json, without writing anything.
+///
+/// `mods` are the metadata of every mod in load order.
+/// Entries are counted per target, so an entry added to every map counts once per map.
+pub fn plan_integration(mods: &[serde_json::Value]) -> serde_json::Value {
+    let mut handlers: BTreeMap<String, Targets> = BTreeMap::new();
+    let mut planned_mods = Vec::new();
+
+    for metadata in mods {
+        let integrator = metadata
+            .get("integrator")
+            .cloned()
+            .unwrap_or_else(|| json!({}));
+
+        let mut entries = BTreeMap::new();
+        for (handler_name, value) in integrator.as_object().into_iter().flatten() {
+            let targets = handlers.entry(handler_name.clone()).or_default();
+            let before: usize = targets.values().sum();
+            handler_targets(handler_name, value, targets);
+            entries.insert(
+                handler_name.clone(),
+                targets.values().sum::<usize>() - before,
+            );
+        }
+
+        planned_mods.push(json!({
+            "mod_id": metadata.get("mod_id"),
+            "version": metadata.get("version"),
+            "entries": entries,
+            "problems": validate_integrator_metadata(&integrator),
+            "compatibility": handler_compatibility_warnings(metadata),
+        }));
+    }
+
+    let handlers: BTreeMap<String, serde_json::Value> = handlers
+        .into_iter()
+        .map(|(name, targets)| {
+            let entries: usize = targets.values().sum();
+            (name, json!({ "targets": targets, "entries": entries }))
+        })
+        .collect();
+
+    json!({
+        "integrator_version": AstroIntegratorConfig::INTEGRATOR_VERSION,
+        "engine_version": game_engine_version(),
+        "mods": planned_mods,
+        "integrator_version_groups": version_groups(mods, "integrator_version"),
+        "engine_version_groups": version_groups(mods, "engine_version"),
+        "handlers": handlers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use unreal_modloader::unreal_asset::ue4version::{VER_UE4_23, VER_UE4_27};
+    use unreal_modloader::unreal_modintegrator::IntegratorConfig;
+
+    use super::{add_to_maps, check_mod_versions, Targets};
+    use crate::AstroIntegratorConfig;
+
+    const MAPS: [&str; 2] = [
+        "Astro/Content/Maps/Staging_T2.umap",
+        "Astro/Content/Maps/Staging_T2_PackedPlanets_Switch.umap",
+    ];
+
+    #[test]
+    fn map_entries_skip_their_excluded_maps() {
+        let entries = json!([
+            "/Game/Mods/Trailhead",
+            { "trailhead": "/Game/Mods/Other", "exclude_maps": ["Staging_T2_PackedPlanets_Switch"] },
+        ]);
+
+        let mut targets = Targets::new();
+        add_to_maps(&mut targets, &MAPS, &entries);
+        assert_eq!(targets.get(MAPS[0]), Some(&2));
+        assert_eq!(targets.get(MAPS[1]), Some(&1));
+    }
+
+    #[test]
+    fn mods_matching_the_running_versions_agree() {
+        let mods = [
+            json!({
+                "mod_id": "A",
+                "integrator_version": AstroIntegratorConfig::INTEGRATOR_VERSION,
+                "engine_version": "4.23",
+            }),
+            json!({ "mod_id": "B" }),
+        ];
+        assert!(check_mod_versions(&mods, VER_UE4_23).is_empty());
+    }
+
+    #[test]
+    fn mismatched_versions_are_reported_with_their_groups() {
+        let mods = [
+            json!({ "mod_id": "A", "integrator_version": "0.0.1", "engine_version": "4.23" }),
+            json!({ "mod_id": "B", "engine_version": "4.27" }),
+        ];
+
+        let problems = check_mod_versions(&mods, VER_UE4_27);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("0.0.1: A"));
+        assert!(problems[1].contains("4.23: A; 4.27: B"));
+    }
+
+    #[test]
+    fn invalid_engine_versions_disagree() {
+        let mods = [json!({ "mod_id": "A", "engine_version": "latest" })];
+        assert_eq!(check_mod_versions(&mods, VER_UE4_23).len(), 1);
+    }
+}
@@ -0,0 +1,68 @@
+use std::io;
+
+use log::info;
+
+use crate::error::IntegratorError;
+
+/// Executable names of the game, the Steam and Microsoft Store builds differ
+const GAME_EXECUTABLES: [&str; 2] = ["Astro-Win64-Shipping.exe", "Astro-UWP64-Shipping.exe"];
+
+/// Find a running game process, returns its executable name
+#[cfg(windows)]
+pub fn find_game_process() -> Option<String> {
+    use std::os::windows::process::CommandExt;
+    use std::process::Command;
+
+    // don't flash a console window
+    const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+    let output = Command::new("tasklist")
+        .args(["/FO", "CSV", "/NH"])
+        .creation_flags(CREATE_NO_WINDOW)
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    output
+        .lines()
+        .filter_map(|e| e.split(',').next())
+        .map(|e| e.trim_matches('"'))
+        .find(|e| {
+            GAME_EXECUTABLES
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(e))
+        })
+        .map(String::from)
+}
+
+/// Find a running game process, returns its executable name.
+///
+/// Under Proton the game shows up as a wine process with the executable in its command line.
+#[cfg(not(windows))]
+pub fn find_game_process() -> Option<String> {
+    let processes = std::fs::read_dir("/proc").ok()?;
+
+    for process in processes.flatten() {
+        let cmdline = match std::fs::read(process.path().join("cmdline")) {
+            Ok(cmdline) => cmdline,
+            Err(_) => continue,
+        };
+        let cmdline = String::from_utf8_lossy(&cmdline);
+
+        if let Some(name) = GAME_EXECUTABLES.iter().find(|e| cmdline.contains(*e)) {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Refuse to integrate while the game is running, it could read a half written pak
+pub(crate) fn ensure_game_not_running() -> Result<(), io::Error> {
+    match find_game_process() {
+        Some(name) => {
+            info!("Found running game process {}", name);
+            Err(IntegratorError::GameRunning { executable: name }.into())
+        }
+        None => Ok(()),
+    }
+}
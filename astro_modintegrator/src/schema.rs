@@ -0,0 +1,99 @@
+use std::{collections::HashMap, io};
+
+use jsonschema::JSONSchema;
+use lazy_static::lazy_static;
+use log::warn;
+
+use crate::{error::IntegratorError, settings::SETTINGS};
+
+/// JSON Schema of the integrator section of a mod's metadata
+pub const METADATA_SCHEMA: &str = include_str!("../schema/metadata.schema.json");
+
+lazy_static! {
+    static ref SCHEMA: JSONSchema = JSONSchema::compile(
+        &serde_json::from_str(METADATA_SCHEMA).expect("Corrupted metadata schema")
+    )
+    .expect("Corrupted metadata schema");
+    static ref HANDLER_SCHEMAS: HashMap<String, JSONSchema> = compile_handler_schemas();
+}
+
+/// Compile a schema for every handler key, so each handler's metadata can be validated on its own
+fn compile_handler_schemas() -> HashMap<String, JSONSchema> {
+    let schema: serde_json::Value =
+        serde_json::from_str(METADATA_SCHEMA).expect("Corrupted metadata schema");
+    let definitions = schema
+        .get("definitions")
+        .cloned()
+        .unwrap_or(serde_json::Value::Null);
+
+    let mut schemas = HashMap::new();
+    if let Some(properties) = schema.get("properties").and_then(|e| e.as_object()) {
+        for (name, property_schema) in properties {
+            let mut property_schema = property_schema.clone();
+            if let Some(property_schema) = property_schema.as_object_mut() {
+                property_schema.insert(String::from("definitions"), definitions.clone());
+            }
+
+            let compiled = JSONSchema::compile(&property_schema)
+                .unwrap_or_else(|e| panic!("Corrupted metadata schema for {}: {}", name, e));
+            schemas.insert(name.clone(), compiled);
+        }
+    }
+    schemas
+}
+
+/// Validate a mod's whole integrator metadata section, returns a description of every problem found
+pub fn validate_integrator_metadata(integrator: &serde_json::Value) -> Vec<String> {
+    match SCHEMA.validate(integrator) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|e| format!("{} {}", e.instance_path, e))
+            .collect(),
+    }
+}
+
+/// Validate the metadata every mod provided for a handler.
+///
+/// Problems are logged with the path to the offending value,
+/// with strict metadata enabled they also fail the integration.
+pub(crate) fn validate_metadata(
+    handler_name: &str,
+    values: &[serde_json::Value],
+) -> Result<(), io::Error> {
+    let schema = match HANDLER_SCHEMAS.get(handler_name) {
+        Some(schema) => schema,
+        None => return Ok(()),
+    };
+
+    let mut problems = Vec::new();
+    for (i, value) in values.iter().enumerate() {
+        if let Err(errors) = schema.validate(value) {
+            for error in errors {
+                problems.push(format!(
+                    "mod #{}: /{}{} {}",
+                    i + 1,
+                    handler_name,
+                    error.instance_path,
+                    error
+                ));
+            }
+        }
+    }
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    for problem in &problems {
+        warn!("Invalid metadata, {}", problem);
+    }
+
+    if SETTINGS.strict_metadata {
+        return Err(IntegratorError::SchemaViolation {
+            handler: handler_name.to_string(),
+            problems,
+        }
+        .into());
+    }
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use std::{collections::HashMap, io};
+
+use log::warn;
+use unreal_modloader::unreal_modintegrator::{
+    helpers::{find_asset, game_to_absolute},
+    IntegratorConfig,
+};
+use unreal_modloader::unreal_pak::PakFile;
+
+use crate::assets::{
+    detect_game_engine_version, game_engine_version, parse_engine_version,
+    set_asset_engine_versions,
+};
+use crate::compat::handler_compatibility_warnings;
+use crate::error::IntegratorError;
+use crate::plan::check_mod_versions;
+use crate::process::ensure_game_not_running;
+use crate::settings::SETTINGS;
+use crate::{
+    handlers::{
+        map_paths, normalize_content_path, report::start_change_log, timing::log_timing_summary,
+    },
+    AstroIntegratorConfig,
+};
+
+/// Entry of a mod pak holding the mod's metadata
+const METADATA_ENTRY: &str = "metadata.json";
+
+/// State shared by every handler over one integration.
+///
+/// The integrator doesn't tell us when integration starts and only hands each handler
+/// its own part of every mod's metadata, so the first handler to run reads the whole
+/// metadata from the mod paks and runs the checks concerning all of it.
+#[derive(Debug, Default)]
+pub(crate) struct IntegrationSession {
+    prepared: bool,
+}
+
+impl IntegrationSession {
+    /// Check the game isn't running, detect its engine version and run the checks
+    /// on every mod's metadata, only the first call does anything
+    #[allow(clippy::ptr_arg)]
+    pub fn prepare(
+        &mut self,
+        game_paks: &mut Vec<PakFile>,
+        mod_paks: &[PakFile],
+    ) -> Result<(), io::Error> {
+        if self.prepared {
+            return Ok(());
+        }
+        // the game could read the pak we are about to write
+        ensure_game_not_running()?;
+        self.prepared = true;
+
+        start_change_log();
+        detect_engine_version(game_paks);
+
+        let mods = read_mod_metadata(mod_paks);
+        log_compatibility_warnings(&mods);
+
+        let problems = check_mod_versions(&mods, game_engine_version());
+        if SETTINGS.strict_metadata && !problems.is_empty() {
+            return Err(IntegratorError::VersionMismatch { problems }.into());
+        }
+        for problem in problems {
+            warn!("{}", problem);
+        }
+
+        set_asset_engine_versions(asset_engine_versions(&mods)?);
+        Ok(())
+    }
+}
+
+impl Drop for IntegrationSession {
+    // the handlers are dropped once integration is done
+    fn drop(&mut self) {
+        log_timing_summary();
+    }
+}
+
+/// Data of a pak entry, `None` if the pak doesn't have it
+fn read_pak_entry(pak: &PakFile, name: &str) -> Option<Vec<u8>> {
+    pak.get_record(&name.to_string())?.data.clone()
+}
+
+/// Detect the engine version from the header of the first map, every game asset shares it
+#[allow(clippy::ptr_arg)]
+fn detect_engine_version(game_paks: &mut Vec<PakFile>) {
+    let map_path = match map_paths().first() {
+        Some(map_path) => map_path.to_string(),
+        None => return detect_game_engine_version("the game's maps", None),
+    };
+    let header =
+        find_asset(game_paks, &map_path).and_then(|e| read_pak_entry(&game_paks[e], &map_path));
+    detect_game_engine_version(&map_path, header.as_deref());
+}
+
+/// Metadata of every mod in load order, paks without readable metadata are left out
+fn read_mod_metadata(mod_paks: &[PakFile]) -> Vec<serde_json::Value> {
+    mod_paks
+        .iter()
+        .filter_map(|pak| read_pak_entry(pak, METADATA_ENTRY))
+        .filter_map(|data| match serde_json::from_slice(&data) {
+            Ok(metadata) => Some(metadata),
+            Err(e) => {
+                warn!("Ignoring unreadable {}: {}", METADATA_ENTRY, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Name of a mod in messages about its metadata
+pub(crate) fn mod_id(metadata: &serde_json::Value) -> &str {
+    metadata
+        .get("mod_id")
+        .and_then(|e| e.as_str())
+        .unwrap_or("<unknown>")
+}
+
+/// Engine versions mods request for assets cooked at another version than the game,
+/// e.g. by total conversion mods, from the `asset_engine_versions` of their metadata.
+///
+/// Keyed by absolute asset path, requesting different versions for an asset is an error.
+fn asset_engine_versions(mods: &[serde_json::Value]) -> Result<HashMap<String, i32>, io::Error> {
+    let mut versions = HashMap::new();
+    for metadata in mods {
+        let requested = match metadata.get("asset_engine_versions") {
+            Some(requested) => requested.as_object().ok_or_else(|| {
+                IntegratorError::invalid_metadata(mod_id(metadata), "asset_engine_versions")
+            })?,
+            None => continue,
+        };
+
+        for (asset_path, version) in requested {
+            let field = format!("asset_engine_versions.{}", asset_path);
+            let version = version
+                .as_str()
+                .ok_or_else(|| IntegratorError::invalid_metadata(mod_id(metadata), &field))?;
+            let version = parse_engine_version(version)?;
+            let asset_name = game_to_absolute(
+                AstroIntegratorConfig::GAME_NAME,
+                &normalize_content_path(asset_path),
+            )
+            .ok_or_else(|| IntegratorError::invalid_metadata(mod_id(metadata), &field))?;
+
+            match versions.insert(asset_name, version) {
+                Some(existing) if existing != version => {
+                    return Err(IntegratorError::ConflictingMetadata {
+                        handler: String::from("asset_engine_versions"),
+                        field: asset_path.clone(),
+                    }
+                    .into())
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(versions)
+}
+
+fn log_compatibility_warnings(mods: &[serde_json::Value]) {
+    for metadata in mods {
+        for warning in handler_compatibility_warnings(metadata) {
+            warn!("{}: {}", mod_id(metadata), warning);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use unreal_modloader::unreal_asset::ue4version::VER_UE4_25;
+
+    use super::asset_engine_versions;
+    use crate::error::IntegratorError;
+
+    #[test]
+    fn asset_engine_versions_are_keyed_by_absolute_path() {
+        let mods = [json!({
+            "mod_id": "A",
+            "asset_engine_versions": { "/Game/Items/ItemList": "4.25" },
+        })];
+
+        let versions = asset_engine_versions(&mods).unwrap();
+        assert_eq!(versions.len(), 1);
+        let (asset_name, version) = versions.iter().next().unwrap();
+        assert!(asset_name.contains("Items/ItemList"));
+        assert_eq!(*version, VER_UE4_25);
+    }
+
+    #[test]
+    fn conflicting_asset_engine_versions_are_rejected() {
+        let mods = [
+            json!({ "mod_id": "A", "asset_engine_versions": { "/Game/Items/ItemList": "4.25" } }),
+            json!({ "mod_id": "B", "asset_engine_versions": { "/Game/Items/ItemList": "4.23" } }),
+        ];
+
+        let error = asset_engine_versions(&mods).unwrap_err();
+        assert!(matches!(
+            IntegratorError::from_io_error(&error),
+            Some(IntegratorError::ConflictingMetadata { .. })
+        ));
+    }
+}
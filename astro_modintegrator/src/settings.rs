@@ -0,0 +1,166 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::{env, fmt::Display, path::PathBuf, str::FromStr};
+
+use lazy_static::lazy_static;
+use log::warn;
+
+lazy_static! {
+    pub static ref SETTINGS: IntegratorSettings = {
+        SETTINGS_READ.store(true, Ordering::SeqCst);
+        CONFIGURED_SETTINGS
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap_or_else(IntegratorSettings::from_env)
+    };
+    static ref CONFIGURED_SETTINGS: Mutex<Option<IntegratorSettings>> = Mutex::new(None);
+}
+
+static SETTINGS_READ: AtomicBool = AtomicBool::new(false);
+
+/// Use `settings` instead of reading them from the environment.
+///
+/// This only works before anything reads [`SETTINGS`], returns `false` when it's too late.
+pub fn configure(settings: IntegratorSettings) -> bool {
+    let mut configured = CONFIGURED_SETTINGS.lock().unwrap();
+    if SETTINGS_READ.load(Ordering::SeqCst) {
+        warn!("Integrator settings were already read, ignoring the configured ones");
+        return false;
+    }
+    *configured = Some(settings);
+    true
+}
+
+/// Settings which change how the integrator behaves
+#[derive(Debug, Clone)]
+pub struct IntegratorSettings {
+    /// Class names of the map exports holding mission data, in order of preference
+    pub mission_data_exports: Vec<String>,
+    /// Fail integration when mod metadata doesn't match the metadata schema
+    pub strict_metadata: bool,
+    /// Also integrate development maps players never load
+    pub include_test_maps: bool,
+    /// Also integrate the tutorial map. It's off by default as every handler editing maps
+    /// has to read and write the tutorial too, which noticeably slows down integration.
+    pub include_tutorial_map: bool,
+    /// Engine version of the game's assets like `4.23`, detected from the game's assets when unset
+    pub engine_version: Option<String>,
+    /// Json list of the maps to integrate, replacing the default maps
+    pub maps_file: Option<PathBuf>,
+    /// Derive the guids of added SCS nodes from their paths instead of generating random ones
+    pub deterministic_guids: bool,
+    /// Write a readable log of every change to `integration_changes.txt`, for mod authors
+    pub change_log: bool,
+    /// Folder the change log is written to, the loader points it at the integrated pak's folder.
+    /// The working directory when unset.
+    pub change_log_dir: Option<PathBuf>,
+    /// Serialize every asset once more before writing it and check its package header survives
+    pub verify_writes: bool,
+    /// Report names in the name map of every written asset that nothing references
+    pub report_orphaned_names: bool,
+    /// Time reading, editing and writing every asset and log the slowest once integration is done
+    pub timings: bool,
+}
+
+impl Default for IntegratorSettings {
+    fn default() -> Self {
+        IntegratorSettings {
+            mission_data_exports: Vec::from([String::from("AstroSettings")]),
+            strict_metadata: false,
+            include_test_maps: false,
+            include_tutorial_map: false,
+            engine_version: None,
+            maps_file: None,
+            deterministic_guids: true,
+            change_log: false,
+            change_log_dir: None,
+            verify_writes: false,
+            report_orphaned_names: false,
+            timings: false,
+        }
+    }
+}
+
+impl IntegratorSettings {
+    /// Read settings from `ASTRO_*` environment variables, falling back to the defaults
+    pub fn from_env() -> Self {
+        let default = IntegratorSettings::default();
+
+        IntegratorSettings {
+            mission_data_exports: env_list(
+                "ASTRO_MISSION_DATA_EXPORTS",
+                default.mission_data_exports,
+            ),
+            strict_metadata: env_flag("ASTRO_STRICT_METADATA", default.strict_metadata),
+            include_test_maps: env_flag("ASTRO_INCLUDE_TEST_MAPS", default.include_test_maps),
+            include_tutorial_map: env_flag(
+                "ASTRO_INCLUDE_TUTORIAL_MAP",
+                default.include_tutorial_map,
+            ),
+            engine_version: env::var("ASTRO_ENGINE_VERSION")
+                .ok()
+                .or(default.engine_version),
+            maps_file: env::var_os("ASTRO_MAPS_FILE")
+                .map(PathBuf::from)
+                .or(default.maps_file),
+            deterministic_guids: env_flag("ASTRO_DETERMINISTIC_GUIDS", default.deterministic_guids),
+            change_log: env_flag("ASTRO_CHANGE_LOG", default.change_log),
+            change_log_dir: env::var_os("ASTRO_CHANGE_LOG_DIR")
+                .map(PathBuf::from)
+                .or(default.change_log_dir),
+            verify_writes: env_flag("ASTRO_VERIFY_WRITES", default.verify_writes),
+            report_orphaned_names: env_flag(
+                "ASTRO_REPORT_ORPHANED_NAMES",
+                default.report_orphaned_names,
+            ),
+            timings: env_flag("ASTRO_TIMINGS", default.timings),
+        }
+    }
+}
+
+/// Read and parse an environment variable, falling back to `default` if it's unset or invalid
+pub fn env_or<T>(key: &str, default: T) -> T
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    match env::var(key) {
+        Ok(value) => match value.parse() {
+            Ok(value) => value,
+            Err(e) => {
+                warn!("Ignoring invalid {}={}: {}", key, value, e);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// Read a flag set to `1`/`true`/`yes` or `0`/`false`/`no`
+pub fn env_flag(key: &str, default: bool) -> bool {
+    match env::var(key) {
+        Ok(value) => match value.to_lowercase().as_str() {
+            "1" | "true" | "yes" => true,
+            "0" | "false" | "no" => false,
+            _ => {
+                warn!("Ignoring invalid {}={}", key, value);
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// Read a comma separated list
+pub fn env_list(key: &str, default: Vec<String>) -> Vec<String> {
+    match env::var(key) {
+        Ok(value) => value
+            .split(',')
+            .map(|e| e.trim())
+            .filter(|e| !e.is_empty())
+            .map(String::from)
+            .collect(),
+        Err(_) => default,
+    }
+}
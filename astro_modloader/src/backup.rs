@@ -0,0 +1,146 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+
+use crate::paths;
+
+/// How many previous versions are kept
+const KEPT_BACKUPS: usize = 3;
+
+fn backups_dir() -> Result<PathBuf, io::Error> {
+    Ok(paths::ensure_config_dir()?.join(paths::BACKUPS_DIR_NAME))
+}
+
+/// Backups in the backups folder, oldest first
+fn list_backups() -> Result<Vec<PathBuf>, io::Error> {
+    let backups_dir = backups_dir()?;
+    if !backups_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = Vec::new();
+    for entry in fs::read_dir(&backups_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().map(|e| e == "bak").unwrap_or(false) {
+            backups.push((entry.metadata()?.modified()?, path));
+        }
+    }
+    backups.sort();
+    Ok(backups.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Copy of the running modloader taken before an update,
+/// only kept as a backup once the update replaced the modloader
+pub struct StagedBackup {
+    path: PathBuf,
+    version: String,
+}
+
+/// Copy the running modloader aside before an update replaces it, see [`StagedBackup`]
+pub fn stage_current_exe(version: &str) -> Result<StagedBackup, io::Error> {
+    let backups_dir = backups_dir()?;
+    paths::create_dir(&backups_dir)?;
+
+    let path = backups_dir.join(format!("astro_modloader-{}.staged", version));
+    fs::copy(std::env::current_exe()?, &path)?;
+    Ok(StagedBackup {
+        path,
+        version: version.to_string(),
+    })
+}
+
+impl StagedBackup {
+    /// Check the update replaced the modloader and keep the copy as a backup,
+    /// dropping the oldest backups beyond the ones kept.
+    ///
+    /// Errors if the modloader wasn't replaced by a plausible build,
+    /// the copy is put back in its place then.
+    pub fn commit(self) -> Result<(), io::Error> {
+        if let Err(e) = verify_replaced(&self.path) {
+            replace_current_exe(&self.path)?;
+            self.discard();
+            return Err(e);
+        }
+
+        let backup_path = self
+            .path
+            .with_file_name(format!("astro_modloader-{}.bak", self.version));
+        if let Err(e) = fs::rename(&self.path, &backup_path) {
+            warn!(
+                "Failed to back up the modloader, rolling back won't be possible: {}",
+                e
+            );
+            return Ok(());
+        }
+        info!("Backed up the modloader to {:?}", backup_path);
+
+        let backups = list_backups()?;
+        for old_backup in &backups[..backups.len().saturating_sub(KEPT_BACKUPS)] {
+            if let Err(e) = fs::remove_file(old_backup) {
+                warn!("Failed to remove old backup {:?}: {}", old_backup, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Throw the copy away, the modloader wasn't updated
+    pub fn discard(self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove {:?}: {}", self.path, e);
+        }
+    }
+}
+
+/// Check that the running modloader was replaced by a non-empty build other than `previous`
+fn verify_replaced(previous: &Path) -> Result<(), io::Error> {
+    let exe = fs::read(std::env::current_exe()?)?;
+    if exe.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "The downloaded modloader is empty, kept the current version",
+        ));
+    }
+    if exe == fs::read(previous)? {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "The update didn't replace the modloader, kept the current version",
+        ));
+    }
+    Ok(())
+}
+
+/// Put a copy of the modloader in place of the running one.
+///
+/// The running executable is moved aside first, as Windows doesn't allow overwriting it.
+fn replace_current_exe(path: &Path) -> Result<(), io::Error> {
+    let exe_path = std::env::current_exe()?;
+    let replaced_path = exe_path.with_extension("old");
+    if replaced_path.is_file() {
+        fs::remove_file(&replaced_path)?;
+    }
+    fs::rename(&exe_path, &replaced_path)?;
+
+    if let Err(e) = fs::copy(path, &exe_path) {
+        // don't leave the user without a modloader
+        fs::rename(&replaced_path, &exe_path)?;
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Put the most recent backup in place of the running modloader
+pub fn restore_latest_backup() -> Result<PathBuf, io::Error> {
+    let backup_path = list_backups()?.pop().ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::NotFound,
+            "There is no previous version to roll back to",
+        )
+    })?;
+
+    replace_current_exe(&backup_path)?;
+    info!("Rolled back the modloader to {:?}", backup_path);
+    Ok(backup_path)
+}
@@ -0,0 +1,96 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref HEADER_REGEX: Regex = Regex::new(r"^#{1,6}\s+(.*?)\s*#*$").unwrap();
+    static ref LIST_REGEX: Regex = Regex::new(r"^(\s*)[-*+]\s+").unwrap();
+    static ref IMAGE_REGEX: Regex = Regex::new(r"!\[([^\]]*)\]\([^)]*\)").unwrap();
+    static ref LINK_REGEX: Regex = Regex::new(r"\[([^\]]+)\]\(([^)\s]+)[^)]*\)").unwrap();
+    static ref EMPHASIS_REGEX: Regex =
+        Regex::new(r"\*\*([^*]+)\*\*|__([^_]+)__|\*([^*\s][^*]*)\*|~~([^~]+)~~").unwrap();
+    static ref CODE_REGEX: Regex = Regex::new(r"`([^`]+)`").unwrap();
+}
+
+/// Strip the inline markup from a line, links keep their target so they can still be followed
+fn render_inline(line: &str) -> String {
+    let line = IMAGE_REGEX.replace_all(line, "$1");
+    let line = LINK_REGEX.replace_all(&line, |captures: &regex::Captures| {
+        match captures[1] == captures[2] {
+            true => captures[1].to_string(),
+            false => format!("{} ({})", &captures[1], &captures[2]),
+        }
+    });
+    let line = EMPHASIS_REGEX.replace_all(&line, |captures: &regex::Captures| {
+        (1..=4)
+            .find_map(|i| captures.get(i))
+            .map(|e| e.as_str().to_string())
+            .unwrap_or_default()
+    });
+    CODE_REGEX.replace_all(&line, "$1").to_string()
+}
+
+/// Turn a GitHub release body written in Markdown into plain text for the update prompt.
+///
+/// Headers lose their `#`, list items become bullets and inline markup is removed.
+pub fn markdown_to_text(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code_block = false;
+
+    for line in markdown.replace("\r\n", "\n").lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            lines.push(format!("    {}", line));
+            continue;
+        }
+
+        let line = match HEADER_REGEX.captures(line) {
+            Some(captures) => render_inline(&captures[1]),
+            None => {
+                let line = LIST_REGEX.replace(line, "$1• ");
+                render_inline(&line)
+            }
+        };
+
+        // collapse runs of blank lines left behind by removed markup
+        if line.trim().is_empty() && lines.last().map(|e: &String| e.is_empty()).unwrap_or(true) {
+            continue;
+        }
+        lines.push(line.trim_end().to_string());
+    }
+
+    lines.join("\n").trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::markdown_to_text;
+
+    #[test]
+    fn headings_and_bullets_are_plain_text() {
+        assert_eq!(
+            markdown_to_text("## What's new ##\r\n\r\n- Added **bold** support\r\n  * nested item"),
+            "What's new\n\n• Added bold support\n  • nested item"
+        );
+    }
+
+    #[test]
+    fn links_keep_their_target() {
+        assert_eq!(
+            markdown_to_text(
+                "See [the docs](https://example.com/docs \"Docs\") or [https://x.y](https://x.y)."
+            ),
+            "See the docs (https://example.com/docs) or https://x.y."
+        );
+    }
+
+    #[test]
+    fn code_spans_and_blocks_are_unwrapped() {
+        assert_eq!(
+            markdown_to_text("Run `astro_modloader --plan`\n\n\n```\nlet x = 1;\n```"),
+            "Run astro_modloader --plan\n\n    let x = 1;"
+        );
+    }
+}
@@ -0,0 +1,412 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use astro_modintegrator::assets::game_engine_version;
+use astro_modintegrator::dump::{
+    dump_asset, list_pak_entries, open_paks, read_loose_asset, read_pak_asset, AssetSource,
+};
+use astro_modintegrator::error::IntegratorError;
+use astro_modintegrator::plan::{check_mod_versions, plan_integration};
+use astro_modintegrator::settings::SETTINGS;
+
+use crate::{config_archive, game_build, mount_order, paths, scaffold, support, AstroGameConfig};
+
+/// Paks to look up a content path in, the pak the game prefers first
+fn source_paks(
+    source: AssetSource,
+    install_path: Option<&Path>,
+) -> Result<Vec<PathBuf>, io::Error> {
+    match source {
+        AssetSource::Game => {
+            let install_path = install_path.ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "No install found, pass one with --install <install folder>",
+                )
+            })?;
+            let paks = mount_order::game_paks(&paths::paks_dir(install_path))?;
+            Ok(paks.into_iter().rev().map(|e| e.path).collect())
+        }
+        AssetSource::Integrated => {
+            let mod_paks_dir = paths::saved_paks_dir().ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::NotFound,
+                    "Could not determine the local data directory",
+                )
+            })?;
+            Ok(Vec::from([
+                mod_paks_dir.join(mount_order::INTEGRATED_PAK_NAME)
+            ]))
+        }
+    }
+}
+
+/// Dump an asset to json, a loose file extracted from a pak,
+/// or a content path like `/Game/Items/ItemList.uasset` looked up in the paks of `source`
+fn dump_asset_to(
+    asset: &str,
+    source: AssetSource,
+    install_path: Option<&Path>,
+    output: &Path,
+) -> Result<(), io::Error> {
+    let asset = match asset.starts_with("/Game/") {
+        true => {
+            let mut paks = open_paks(&source_paks(source, install_path)?)?;
+            read_pak_asset(&mut paks, asset)?
+        }
+        false => read_loose_asset(Path::new(asset))?,
+    };
+    let dump = serde_json::to_string_pretty(&dump_asset(&asset))?;
+    fs::write(output, dump)
+}
+
+/// Describe the assets in the integrated pak, so users can see whether integration wrote anything
+fn list_integrated(pak_path: &Path) -> Result<String, io::Error> {
+    let entries = list_pak_entries(pak_path)?;
+    let total_size: u64 = entries.iter().map(|(_, size)| size).sum();
+
+    let mut description = format!("{} assets in {:?}:\n", entries.len(), pak_path);
+    for (name, size) in &entries {
+        description.push_str(&format!("{:>10} {}\n", size, name));
+    }
+    description.push_str(&format!("{} bytes in total", total_size));
+    Ok(description)
+}
+
+/// Plan the integration of mods given by their metadata files, in load order
+fn plan(install_path: &Path, metadata_paths: &[String]) -> Result<serde_json::Value, io::Error> {
+    let mut mods = Vec::new();
+    for metadata_path in metadata_paths {
+        let metadata = fs::read_to_string(metadata_path)?;
+        mods.push(serde_json::from_str(&metadata)?);
+    }
+
+    let game_build = game_build::read_build_name(install_path);
+
+    let problems = check_mod_versions(&mods, game_engine_version());
+    if SETTINGS.strict_metadata && !problems.is_empty() {
+        return Err(IntegratorError::VersionMismatch { problems }.into());
+    }
+    for problem in problems {
+        eprintln!("Warning: {}", problem);
+    }
+
+    let mut plan = plan_integration(&mods);
+    plan["game_build"] = serde_json::Value::from(game_build);
+    Ok(plan)
+}
+
+/// Exit code of a command that failed
+const EXIT_FAILURE: i32 = 1;
+/// Exit code of a command missing its arguments
+const EXIT_USAGE: i32 = 2;
+
+/// Release builds have no console of their own, attach to the one the modloader was started from
+/// so commands can print there
+#[cfg(windows)]
+fn attach_console() {
+    use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
+
+    // fails when started from explorer, there's nothing to print to then
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
+/// Run a command given on the command line instead of starting the modloader.
+///
+/// Returns the exit code of the command, `None` if no command was given.
+pub fn run_command(args: &[String]) -> Option<i32> {
+    if args.len() > 1 {
+        attach_console();
+    }
+
+    if args.iter().any(|e| e == "--collect-support-bundle") {
+        let scrub_paths = args.iter().any(|e| e == "--scrub-paths");
+        return match support::collect_support_bundle(scrub_paths) {
+            Ok(path) => {
+                println!("Support bundle written to {:?}", path);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to collect support bundle: {}", e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if args.iter().any(|e| e == "--inspect") {
+        let scrub_paths = args.iter().any(|e| e == "--scrub-paths");
+        let report = support::inspect_report(scrub_paths);
+        println!("{}", report);
+        return match fs::write(support::INSPECT_REPORT_FILE_NAME, &report) {
+            Ok(()) => {
+                println!("Report written to {}", support::INSPECT_REPORT_FILE_NAME);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to write the report: {}", e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if let Some(index) = args.iter().position(|e| e == "--dump-asset") {
+        let usage = "Usage: --dump-asset <asset.uasset | /Game/content/path.uasset> [output.json] \
+                     [--source game|integrated] [--install <install folder>]";
+        let option = |name: &str| {
+            args.iter()
+                .position(|e| e == name)
+                .and_then(|e| args.get(e + 1))
+        };
+        let source = match option("--source").map(|e| e.parse::<AssetSource>()) {
+            Some(Ok(source)) => source,
+            Some(Err(e)) => {
+                eprintln!("{}\n{}", e, usage);
+                return Some(EXIT_USAGE);
+            }
+            None => AssetSource::Game,
+        };
+        let install_path = option("--install").map(PathBuf::from).or_else(|| {
+            AstroGameConfig
+                .detect_install_paths()
+                .into_values()
+                .flatten()
+                .next()
+        });
+
+        // the asset and output come first, the options after them
+        let mut positional = args[index + 1..]
+            .iter()
+            .take_while(|e| !e.starts_with("--"));
+        let asset = match positional.next() {
+            Some(asset) => asset,
+            None => {
+                eprintln!("{}", usage);
+                return Some(EXIT_USAGE);
+            }
+        };
+        let output = positional.next().map(PathBuf::from).unwrap_or_else(|| {
+            match asset.starts_with("/Game/") {
+                true => PathBuf::from(Path::new(asset).file_name().unwrap_or_default())
+                    .with_extension("json"),
+                false => Path::new(asset).with_extension("json"),
+            }
+        });
+
+        return match dump_asset_to(asset, source, install_path.as_deref(), &output) {
+            Ok(()) => {
+                println!("Dumped {} to {:?}", asset, output);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to dump {}: {}", asset, e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if let Some(index) = args.iter().position(|e| e == "--scaffold-mod") {
+        let mod_id = match args.get(index + 1) {
+            Some(mod_id) => mod_id,
+            None => {
+                eprintln!("Usage: --scaffold-mod <ModId> [output folder]");
+                return Some(EXIT_USAGE);
+            }
+        };
+        let output = args
+            .get(index + 2)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        return match scaffold::scaffold_mod(mod_id, &output) {
+            Ok(path) => {
+                println!("Mod scaffold written to {:?}", path);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to scaffold {}: {}", mod_id, e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if let Some(index) = args.iter().position(|e| e == "--mount-order") {
+        let install_path = match args.get(index + 1) {
+            Some(install_path) => PathBuf::from(install_path),
+            None => {
+                eprintln!("Usage: --mount-order <install folder> [integrated pak name]");
+                return Some(EXIT_USAGE);
+            }
+        };
+        let integrated_pak_name = args
+            .get(index + 2)
+            .map(|e| e.as_str())
+            .unwrap_or(mount_order::INTEGRATED_PAK_NAME);
+        let mod_paks_dir = match paths::saved_paks_dir() {
+            Some(mod_paks_dir) => mod_paks_dir,
+            None => {
+                eprintln!("Could not determine the local data directory");
+                return Some(EXIT_FAILURE);
+            }
+        };
+
+        return match mount_order::mount_order(&paths::paks_dir(&install_path), &mod_paks_dir) {
+            Ok(paks) => {
+                println!(
+                    "{}",
+                    mount_order::describe_mount_order(&paks, integrated_pak_name)
+                );
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to list paks: {}", e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if let Some(index) = args.iter().position(|e| e == "--list-integrated") {
+        let pak_path = match args.get(index + 1) {
+            Some(pak_path) => PathBuf::from(pak_path),
+            None => match paths::saved_paks_dir() {
+                Some(mod_paks_dir) => mod_paks_dir.join(mount_order::INTEGRATED_PAK_NAME),
+                None => {
+                    eprintln!("Could not determine the local data directory");
+                    return Some(EXIT_FAILURE);
+                }
+            },
+        };
+
+        return match list_integrated(&pak_path) {
+            Ok(description) => {
+                println!("{}", description);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to read {:?}: {}", pak_path, e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if let Some(index) = args.iter().position(|e| e == "--plan") {
+        let install_path = match args.get(index + 1) {
+            Some(install_path) => PathBuf::from(install_path),
+            None => {
+                eprintln!("Usage: --plan <install folder> [metadata.json...]");
+                return Some(EXIT_USAGE);
+            }
+        };
+
+        return match plan(&install_path, &args[index + 2..]) {
+            Ok(plan) => {
+                println!("{}", serde_json::to_string_pretty(&plan).unwrap());
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to plan the integration: {}", e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if args.iter().any(|e| e == "--list-installs") {
+        let mut found = false;
+        for (manager, install_path) in AstroGameConfig.detect_install_paths() {
+            match install_path {
+                Some(install_path) => {
+                    println!("{}: {:?}", manager, install_path);
+                    found = true;
+                }
+                None => println!("{}: not found", manager),
+            }
+        }
+        return Some(if found { 0 } else { EXIT_FAILURE });
+    }
+
+    if let Some(index) = args.iter().position(|e| e == "--export-config") {
+        let archive_path = match args.get(index + 1) {
+            Some(archive_path) => PathBuf::from(archive_path),
+            None => {
+                eprintln!("Usage: --export-config <archive.zip>");
+                return Some(EXIT_USAGE);
+            }
+        };
+
+        return match config_archive::export_config(&archive_path) {
+            Ok(files) => {
+                println!("Exported {} config files to {:?}", files, archive_path);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to export the config: {}", e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if let Some(index) = args.iter().position(|e| e == "--import-config") {
+        let archive_path = match args.get(index + 1) {
+            Some(archive_path) => PathBuf::from(archive_path),
+            None => {
+                eprintln!("Usage: --import-config <archive.zip>");
+                return Some(EXIT_USAGE);
+            }
+        };
+
+        return match config_archive::import_config(&archive_path) {
+            Ok(problems) if problems.is_empty() => {
+                println!("Imported {:?}", archive_path);
+                Some(0)
+            }
+            Ok(problems) => {
+                println!("Imported {:?}, with problems:", archive_path);
+                for problem in problems {
+                    println!("  {}", problem);
+                }
+                Some(EXIT_FAILURE)
+            }
+            Err(e) => {
+                eprintln!("Failed to import the config: {}", e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if args.iter().any(|e| e == "--rollback") {
+        return match AstroGameConfig.rollback_modloader() {
+            Ok(backup_path) => {
+                println!("Restored {:?}", backup_path);
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to roll back: {}", e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    if args.iter().any(|e| e == "--changelog") {
+        return match AstroGameConfig.get_update_changelog() {
+            Ok(Some((version, changelog))) => {
+                println!("What's new in {}\n\n{}", version, changelog);
+                Some(0)
+            }
+            Ok(None) => {
+                println!("No update available");
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("Failed to check for updates: {}", e);
+                Some(EXIT_FAILURE)
+            }
+        };
+    }
+
+    None
+}
@@ -0,0 +1,214 @@
+use std::fs;
+use std::io::{self, prelude::*, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::paths;
+use crate::settings::{LoaderSettings, SETTINGS_FILE_NAME};
+
+fn map_zip_err(e: zip::result::ZipError) -> io::Error {
+    io::Error::new(ErrorKind::Other, e)
+}
+
+fn config_dir() -> Result<PathBuf, io::Error> {
+    paths::config_dir().ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::NotFound,
+            "Could not determine the local data directory",
+        )
+    })
+}
+
+/// Every file below `dir`, relative to `root`
+fn collect_files(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), io::Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_path_buf());
+        }
+    }
+    Ok(())
+}
+
+/// Absolute paths in a json value that don't exist on this machine
+fn missing_paths(value: &serde_json::Value, missing: &mut Vec<String>) {
+    match value {
+        serde_json::Value::String(text) => {
+            let path = Path::new(text);
+            if path.is_absolute() && !path.exists() {
+                missing.push(text.clone());
+            }
+        }
+        serde_json::Value::Array(values) => values.iter().for_each(|e| missing_paths(e, missing)),
+        serde_json::Value::Object(values) => {
+            values.values().for_each(|e| missing_paths(e, missing))
+        }
+        _ => {}
+    }
+}
+
+/// Check a config file from an archive before it is written,
+/// returns an error if it can't be applied and warnings about parts that may not work here
+fn check_config_file(name: &Path, data: &[u8]) -> Result<Vec<String>, String> {
+    if name.extension().map(|e| e != "json").unwrap_or(true) {
+        return Ok(Vec::new());
+    }
+
+    let value: serde_json::Value =
+        serde_json::from_slice(data).map_err(|e| format!("invalid json: {}", e))?;
+    if name == Path::new(SETTINGS_FILE_NAME) {
+        serde_json::from_value::<LoaderSettings>(value.clone())
+            .map_err(|e| format!("invalid settings: {}", e))?;
+    }
+
+    let mut missing = Vec::new();
+    missing_paths(&value, &mut missing);
+    Ok(missing
+        .into_iter()
+        .map(|e| format!("{:?} doesn't exist on this machine", e))
+        .collect())
+}
+
+/// Write the whole config directory, settings and mod state alike, to a single zip
+pub fn export_config(archive_path: &Path) -> Result<usize, io::Error> {
+    let config_dir = config_dir()?;
+    let mut files = Vec::new();
+    collect_files(&config_dir, &config_dir, &mut files)?;
+    files.sort();
+
+    let mut zip = ZipWriter::new(fs::File::create(archive_path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+    for file in &files {
+        let name = file
+            .components()
+            .map(|e| e.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        zip.start_file(name, options).map_err(map_zip_err)?;
+        zip.write_all(&fs::read(config_dir.join(file))?)?;
+    }
+    zip.finish().map_err(map_zip_err)?;
+
+    Ok(files.len())
+}
+
+/// Files of a config archive that can be applied, with everything that was left out
+/// or may not work here
+type CheckedArchive = (Vec<(PathBuf, Vec<u8>)>, Vec<String>);
+
+/// Read and check every entry of a config archive, see [`import_config`]
+fn read_config_archive<R: Read + Seek>(reader: R) -> Result<CheckedArchive, io::Error> {
+    let mut archive = ZipArchive::new(reader).map_err(map_zip_err)?;
+
+    let mut problems = Vec::new();
+    let mut files = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i).map_err(map_zip_err)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = match file.enclosed_name() {
+            Some(name) => name.to_path_buf(),
+            None => {
+                problems.push(format!("{}: unsafe path, skipped", file.name()));
+                continue;
+            }
+        };
+
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        match check_config_file(&name, &data) {
+            Ok(warnings) => {
+                problems.extend(
+                    warnings
+                        .into_iter()
+                        .map(|e| format!("{}: {}", name.display(), e)),
+                );
+                files.push((name, data));
+            }
+            Err(e) => problems.push(format!("{}: {}, skipped", name.display(), e)),
+        }
+    }
+    Ok((files, problems))
+}
+
+/// Apply a config archive made by [`export_config`] to this machine's config directory.
+///
+/// Every entry is checked before anything is written, entries that can't be applied
+/// are left out. Returns a description of everything that was left out or may not work here.
+pub fn import_config(archive_path: &Path) -> Result<Vec<String>, io::Error> {
+    let (files, problems) = read_config_archive(fs::File::open(archive_path)?)?;
+    if files.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("{:?} contains no config that can be applied", archive_path),
+        ));
+    }
+
+    let config_dir = paths::ensure_config_dir()?;
+    for (name, data) in files {
+        let path = config_dir.join(name);
+        if let Some(parent) = path.parent() {
+            paths::create_dir(parent)?;
+        }
+        fs::write(path, data)?;
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+    use std::path::PathBuf;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::read_config_archive;
+    use crate::settings::SETTINGS_FILE_NAME;
+
+    fn archive(entries: &[(&str, &str)]) -> Cursor<Vec<u8>> {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(data.as_bytes()).unwrap();
+        }
+        let mut archive = zip.finish().unwrap();
+        archive.set_position(0);
+        archive
+    }
+
+    #[test]
+    fn entries_outside_the_config_directory_are_skipped() {
+        let (files, problems) = read_config_archive(archive(&[
+            ("../outside.json", "{}"),
+            ("mods/state.json", "{}"),
+        ]))
+        .unwrap();
+
+        let names: Vec<&PathBuf> = files.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, [&PathBuf::from("mods").join("state.json")]);
+        assert_eq!(problems, ["../outside.json: unsafe path, skipped"]);
+    }
+
+    #[test]
+    fn invalid_config_files_are_skipped_and_missing_paths_reported() {
+        let missing_path = std::env::temp_dir().join("astro_config_archive_missing_game");
+        let settings = serde_json::json!({ "game_path": missing_path }).to_string();
+        let (files, problems) = read_config_archive(archive(&[
+            (SETTINGS_FILE_NAME, settings.as_str()),
+            ("maps.json", "[not json"),
+            ("notes.txt", "anything"),
+        ]))
+        .unwrap();
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(problems.len(), 2);
+        assert!(problems[0].contains("doesn't exist on this machine"));
+        assert!(problems[1].starts_with("maps.json: invalid json"));
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use astro_modintegrator::unreal_modloader::config::InstallManager;
+use astro_modintegrator::unreal_modloader::game_platform_managers::GetGameBuildTrait;
+use astro_modintegrator::unreal_modloader::version::GameBuild;
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+
+use crate::settings::SETTINGS;
+
+/// Number of components of a game build, like `1.24.29.0`
+const BUILD_COMPONENTS: usize = 4;
+
+lazy_static! {
+    /// Builds read so far by install path, shared by all managers so every install is read once
+    static ref GAME_BUILDS: Mutex<HashMap<PathBuf, GameBuild>> = Mutex::new(HashMap::new());
+}
+
+/// Whether the broken_builds setting lists a game build as breaking integration
+fn is_broken_build(game_build: &str) -> bool {
+    SETTINGS.broken_builds.iter().any(|e| e == game_build)
+}
+
+/// Turn the raw build string into `major.minor.patch.build` with plain numbers,
+/// `None` if it doesn't have that shape
+fn normalize_build(raw: &str) -> Option<String> {
+    let components: Vec<&str> = raw.split('.').collect();
+    if components.len() != BUILD_COMPONENTS {
+        return None;
+    }
+
+    let components = components
+        .iter()
+        .map(|e| e.parse::<u32>().ok().map(|e| e.to_string()))
+        .collect::<Option<Vec<String>>>()?;
+    Some(components.join("."))
+}
+
+/// The game build from a normalized build string, warning about known broken builds
+pub fn checked_game_build(normalized: &str) -> Option<GameBuild> {
+    if is_broken_build(normalized) {
+        error!(
+            "Game build {} is listed in the broken_builds setting as breaking mod integration, integrated mods may corrupt the game",
+            normalized
+        );
+        if SETTINGS.refuse_broken_builds {
+            return None;
+        }
+    }
+
+    GameBuild::try_from(&normalized.to_string()).ok()
+}
+
+/// The normalized build string from the contents of `build.version`.
+///
+/// The build is the first word of the file, malformed builds are rejected
+/// so they never end up in compatibility checks.
+pub fn normalized_game_build(version_file: &str) -> Option<String> {
+    let raw = version_file
+        .trim_start_matches('\u{feff}')
+        .split_whitespace()
+        .next()
+        .unwrap_or("");
+
+    let normalized = match normalize_build(raw) {
+        Some(normalized) => normalized,
+        None => {
+            warn!(
+                "Ignoring malformed game build {:?}, expected {} numbers separated by dots",
+                raw, BUILD_COMPONENTS
+            );
+            return None;
+        }
+    };
+    if normalized != raw {
+        info!("Normalized game build {:?} to {}", raw, normalized);
+    }
+    Some(normalized)
+}
+
+/// Read the game build from `build.version` in an install.
+///
+/// A missing, unreadable or malformed file is logged and gives `None`, the build is then unknown.
+pub fn read_game_build(install_path: &Path) -> Option<GameBuild> {
+    checked_game_build(&read_build_name(install_path)?)
+}
+
+/// Read the normalized build string from `build.version` in an install, see [`read_game_build`]
+pub fn read_build_name(install_path: &Path) -> Option<String> {
+    let version_file_path = install_path.join("build.version");
+    if !version_file_path.is_file() {
+        info!("{:?} not found", version_file_path);
+        return None;
+    }
+
+    let version_file = match fs::read_to_string(&version_file_path) {
+        Ok(version_file) => version_file,
+        Err(e) => {
+            warn!("Failed to read {:?}: {}", version_file_path, e);
+            return None;
+        }
+    };
+    normalized_game_build(&version_file)
+}
+
+/// Reads the build of whatever install a manager found, caching it per install
+#[derive(Debug)]
+pub struct BuildVersionReader<M> {
+    manager: PhantomData<fn(&M)>,
+}
+
+impl<M> Default for BuildVersionReader<M> {
+    fn default() -> Self {
+        BuildVersionReader {
+            manager: PhantomData,
+        }
+    }
+}
+
+impl<M: InstallManager> GetGameBuildTrait<M> for BuildVersionReader<M> {
+    fn get_game_build(&self, manager: &M) -> Option<GameBuild> {
+        let install_path = manager.get_game_install_path()?;
+
+        let mut game_builds = GAME_BUILDS.lock().unwrap();
+        if let Some(game_build) = game_builds.get(&install_path) {
+            return Some(*game_build);
+        }
+
+        let game_build = read_game_build(&install_path)?;
+        game_builds.insert(install_path, game_build);
+        Some(game_build)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_build, normalized_game_build};
+
+    #[test]
+    fn four_component_builds_are_kept() {
+        assert_eq!(normalize_build("1.24.29.0").as_deref(), Some("1.24.29.0"));
+        assert_eq!(
+            normalize_build("1.024.029.00").as_deref(),
+            Some("1.24.29.0")
+        );
+    }
+
+    #[test]
+    fn three_component_builds_are_rejected() {
+        assert_eq!(normalize_build("1.24.29"), None);
+    }
+
+    #[test]
+    fn non_numeric_builds_are_rejected() {
+        assert_eq!(normalize_build("1.24.29.x"), None);
+        assert_eq!(normalize_build("1.24.-29.0"), None);
+        assert_eq!(normalize_build(""), None);
+    }
+
+    #[test]
+    fn version_files_are_trimmed() {
+        assert_eq!(
+            normalized_game_build("\u{feff}1.24.29.0 Steam\r\n").as_deref(),
+            Some("1.24.29.0")
+        );
+    }
+}
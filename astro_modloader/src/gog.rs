@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+use astro_modintegrator::unreal_modloader::config::InstallManager;
+use astro_modintegrator::unreal_modloader::error::ModLoaderError;
+use astro_modintegrator::unreal_modloader::game_platform_managers::GetGameBuildTrait;
+use astro_modintegrator::unreal_modloader::version::GameBuild;
+use log::{debug, info};
+
+use crate::paths;
+#[cfg(windows)]
+use crate::settings::SETTINGS;
+
+/// Name GOG gives Astroneer, GOG Galaxy installs are only matched by it
+/// when no product id is set in the settings
+const GOG_GAME_NAME: &str = "ASTRONEER";
+
+/// Whether the GOG Galaxy game registered under `game_id` is Astroneer
+#[cfg(windows)]
+fn is_astroneer(game_id: &str, game: &winreg::RegKey) -> bool {
+    match &SETTINGS.gog_game_id {
+        // the key is named after the product id, `gameID` holds it as well
+        Some(gog_game_id) => {
+            let registered_id: String = game.get_value("gameID").unwrap_or_default();
+            game_id == gog_game_id || registered_id == *gog_game_id
+        }
+        None => {
+            let game_name: String = game.get_value("gameName").unwrap_or_default();
+            game_name.eq_ignore_ascii_case(GOG_GAME_NAME)
+        }
+    }
+}
+
+/// Find the install GOG Galaxy registered under `HKLM\SOFTWARE\WOW6432Node\GOG.com\Games`
+#[cfg(windows)]
+fn find_install() -> Option<PathBuf> {
+    use winreg::enums::HKEY_LOCAL_MACHINE;
+    use winreg::RegKey;
+
+    let games = match RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey("SOFTWARE\\WOW6432Node\\GOG.com\\Games")
+    {
+        Ok(games) => games,
+        Err(e) => {
+            debug!("No GOG games registered: {}", e);
+            return None;
+        }
+    };
+
+    for game_id in games.enum_keys().flatten() {
+        let game = match games.open_subkey(&game_id) {
+            Ok(game) => game,
+            Err(_) => continue,
+        };
+        if !is_astroneer(&game_id, &game) {
+            continue;
+        }
+
+        match game.get_value::<String, _>("path") {
+            Ok(path) => {
+                info!("Found GOG install of game {}", game_id);
+                return Some(PathBuf::from(path));
+            }
+            Err(e) => debug!("GOG game {} has no path: {}", game_id, e),
+        }
+    }
+    None
+}
+
+/// Find an install from the offline installer in `~/GOG Games`,
+/// which puts the game itself either in the game folder or in its `game` subfolder
+#[cfg(target_os = "linux")]
+fn find_install() -> Option<PathBuf> {
+    let gog_games = PathBuf::from(std::env::var("HOME").ok()?).join("GOG Games");
+    let entries = match std::fs::read_dir(&gog_games) {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("Failed to read {:?}: {}", gog_games, e);
+            return None;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().replace(' ', "");
+        if !name.eq_ignore_ascii_case(GOG_GAME_NAME) {
+            continue;
+        }
+
+        let game_dir = entry.path();
+        for install_path in [game_dir.join("game"), game_dir] {
+            if install_path.join("build.version").is_file() {
+                info!("Found GOG install at {:?}", install_path);
+                return Some(install_path);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+fn find_install() -> Option<PathBuf> {
+    None
+}
+
+/// Astroneer installed from GOG
+#[derive(Debug)]
+pub struct GogInstallManager {
+    pub game_path: RefCell<Option<PathBuf>>,
+    get_game_build: Box<dyn GetGameBuildTrait<GogInstallManager>>,
+}
+
+impl GogInstallManager {
+    pub fn new(get_game_build: Box<dyn GetGameBuildTrait<GogInstallManager>>) -> Self {
+        GogInstallManager {
+            game_path: RefCell::new(None),
+            get_game_build,
+        }
+    }
+}
+
+impl InstallManager for GogInstallManager {
+    fn get_game_install_path(&self) -> Option<PathBuf> {
+        if self.game_path.borrow().is_none() {
+            *self.game_path.borrow_mut() = find_install();
+        }
+        self.game_path.borrow().clone()
+    }
+
+    fn get_paks_path(&self) -> Option<PathBuf> {
+        paths::saved_paks_dir()
+    }
+
+    fn get_game_build(&self) -> Option<GameBuild> {
+        self.get_game_build.get_game_build(self)
+    }
+
+    fn launch_game(&self) -> Result<(), ModLoaderError> {
+        let install_path = self
+            .get_game_install_path()
+            .ok_or_else(|| ModLoaderError::other(String::from("GOG install not found")))?;
+
+        paths::launch_executable(&install_path).map_err(|e| ModLoaderError::other(e.to_string()))
+    }
+}
@@ -0,0 +1,198 @@
+use std::io::{self, ErrorKind};
+
+use astro_modintegrator::unreal_modloader::config::IconData;
+use image::ImageFormat;
+
+/// Icon sizes that render well as a window icon, in order of preference
+const PREFERRED_SIZES: [u32; 2] = [32, 48];
+
+const ICO_HEADER_SIZE: usize = 6;
+const ICO_ENTRY_SIZE: usize = 16;
+
+struct IcoEntry<'data> {
+    width: u32,
+    height: u32,
+    bits_per_pixel: u16,
+    header: &'data [u8],
+    data: &'data [u8],
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+/// Sizes are stored in a byte, 0 means 256 pixels
+fn ico_dimension(size: u8) -> u32 {
+    match size {
+        0 => 256,
+        size => size as u32,
+    }
+}
+
+/// Read the directory of an `.ico`, entries pointing outside the file are dropped
+fn read_ico_entries(data: &[u8]) -> Result<Vec<IcoEntry>, io::Error> {
+    if data.len() < ICO_HEADER_SIZE || read_u16(data, 2) != 1 {
+        return Err(io::Error::new(ErrorKind::InvalidData, "Invalid ico header"));
+    }
+
+    let count = read_u16(data, 4) as usize;
+    let mut entries = Vec::with_capacity(count);
+    for i in 0..count {
+        let start = ICO_HEADER_SIZE + i * ICO_ENTRY_SIZE;
+        let header = match data.get(start..start + ICO_ENTRY_SIZE) {
+            Some(header) => header,
+            None => break,
+        };
+
+        let size = read_u32(header, 8) as usize;
+        let offset = read_u32(header, 12) as usize;
+        let image_data = match offset
+            .checked_add(size)
+            .and_then(|end| data.get(offset..end))
+        {
+            Some(image_data) => image_data,
+            None => continue,
+        };
+
+        entries.push(IcoEntry {
+            width: ico_dimension(header[0]),
+            height: ico_dimension(header[1]),
+            bits_per_pixel: read_u16(header, 6),
+            header,
+            data: image_data,
+        });
+    }
+    Ok(entries)
+}
+
+/// Pick the entry closest to the preferred sizes, falling back to the largest one
+fn pick_ico_entry<'a, 'data>(entries: &'a [IcoEntry<'data>]) -> Option<&'a IcoEntry<'data>> {
+    PREFERRED_SIZES
+        .iter()
+        .find_map(|size| {
+            entries
+                .iter()
+                .filter(|e| e.width == *size && e.height == *size)
+                .max_by_key(|e| e.bits_per_pixel)
+        })
+        .or_else(|| {
+            entries
+                .iter()
+                .max_by_key(|e| (e.width * e.height, e.bits_per_pixel))
+        })
+}
+
+/// Decode a single frame of an `.ico`.
+///
+/// The decoder always picks the largest frame, so the chosen entry
+/// is repacked into an `.ico` of its own before decoding.
+fn decode_ico(data: &[u8]) -> Result<image::RgbaImage, io::Error> {
+    let entries = read_ico_entries(data)?;
+    let entry = pick_ico_entry(&entries)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "Ico contains no images"))?;
+
+    let mut single = Vec::with_capacity(ICO_HEADER_SIZE + ICO_ENTRY_SIZE + entry.data.len());
+    single.extend_from_slice(&[0, 0, 1, 0, 1, 0]);
+    single.extend_from_slice(&entry.header[..12]);
+    single.extend_from_slice(&((ICO_HEADER_SIZE + ICO_ENTRY_SIZE) as u32).to_le_bytes());
+    single.extend_from_slice(entry.data);
+
+    image::load_from_memory_with_format(&single, ImageFormat::Ico)
+        .map(|e| e.to_rgba8())
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Decode a window icon, PNG and ICO are told apart by their contents
+pub fn decode_icon(data: &[u8]) -> Result<IconData, io::Error> {
+    let format =
+        image::guess_format(data).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+    let image = match format {
+        ImageFormat::Ico => decode_ico(data)?,
+        ImageFormat::Png => image::load_from_memory_with_format(data, ImageFormat::Png)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?
+            .to_rgba8(),
+        _ => {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("Unsupported icon format {:?}", format),
+            ))
+        }
+    };
+
+    Ok(IconData {
+        width: image.width(),
+        height: image.height(),
+        data: image.into_raw(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::{DynamicImage, ImageFormat, RgbaImage};
+
+    use super::{decode_icon, ICO_ENTRY_SIZE, ICO_HEADER_SIZE};
+
+    fn png(size: u32) -> Vec<u8> {
+        let mut data = Cursor::new(Vec::new());
+        DynamicImage::ImageRgba8(RgbaImage::new(size, size))
+            .write_to(&mut data, ImageFormat::Png)
+            .unwrap();
+        data.into_inner()
+    }
+
+    /// An `.ico` with a PNG frame of every size, in the given order
+    fn ico(sizes: &[u32]) -> Vec<u8> {
+        let frames: Vec<Vec<u8>> = sizes.iter().map(|e| png(*e)).collect();
+
+        let mut data = Vec::from([0, 0, 1, 0]);
+        data.extend_from_slice(&(sizes.len() as u16).to_le_bytes());
+        let mut offset = ICO_HEADER_SIZE + sizes.len() * ICO_ENTRY_SIZE;
+        for (size, frame) in sizes.iter().zip(&frames) {
+            data.extend_from_slice(&[*size as u8, *size as u8, 0, 0]);
+            data.extend_from_slice(&1u16.to_le_bytes());
+            data.extend_from_slice(&32u16.to_le_bytes());
+            data.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            data.extend_from_slice(&(offset as u32).to_le_bytes());
+            offset += frame.len();
+        }
+        for frame in frames {
+            data.extend_from_slice(&frame);
+        }
+        data
+    }
+
+    #[test]
+    fn a_preferred_ico_frame_is_picked_over_larger_ones() {
+        let icon = decode_icon(&ico(&[16, 64, 48, 32])).unwrap();
+        assert_eq!((icon.width, icon.height), (32, 32));
+
+        let icon = decode_icon(&ico(&[16, 64, 48])).unwrap();
+        assert_eq!((icon.width, icon.height), (48, 48));
+    }
+
+    #[test]
+    fn the_largest_ico_frame_is_the_fallback() {
+        let icon = decode_icon(&ico(&[16, 64, 24])).unwrap();
+        assert_eq!((icon.width, icon.height), (64, 64));
+        assert_eq!(icon.data.len(), 64 * 64 * 4);
+    }
+
+    #[test]
+    fn pngs_are_decoded_as_is() {
+        let icon = decode_icon(&png(20)).unwrap();
+        assert_eq!((icon.width, icon.height), (20, 20));
+        assert!(decode_icon(b"not an icon").is_err());
+    }
+}
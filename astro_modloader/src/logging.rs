@@ -1,9 +1,12 @@
 use std::fs;
 use std::io::prelude::*;
+use std::path::PathBuf;
 
 use colored::*;
 use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
 
+use crate::paths;
+
 #[derive(Debug)]
 struct SimpleLogger;
 
@@ -70,6 +73,18 @@ impl log::Log for SimpleLogger {
     fn flush(&self) {}
 }
 
+pub const LOG_FILE_NAME: &str = "modloader_log.txt";
+
+/// The log in the config directory's logs folder,
+/// or the working directory if the config directory couldn't be created
+pub fn log_file_path() -> PathBuf {
+    paths::config_dir()
+        .map(|e| e.join(paths::LOGS_DIR_NAME))
+        .filter(|e| e.is_dir())
+        .map(|e| e.join(LOG_FILE_NAME))
+        .unwrap_or_else(|| PathBuf::from(LOG_FILE_NAME))
+}
+
 static LOGGER: SimpleLogger = SimpleLogger;
 static mut LOG_FILE: Option<fs::File> = None;
 
@@ -82,7 +97,7 @@ pub fn init() -> Result<(), SetLoggerError> {
                 .write(true)
                 .create(true)
                 .truncate(true)
-                .open("modloader_log.txt")
+                .open(log_file_path())
                 .unwrap(),
         );
     }
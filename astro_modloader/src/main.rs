@@ -1,114 +1,127 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::cell::RefCell;
 use std::collections::BTreeMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
 
 use astro_modintegrator::unreal_modintegrator::IntegratorConfig;
 use astro_modintegrator::unreal_modloader::config::{GameConfig, IconData, InstallManager};
 use astro_modintegrator::unreal_modloader::error::ModLoaderError;
-use astro_modintegrator::unreal_modloader::game_platform_managers::{GetGameBuildTrait, SteamInstallManager, ProtonInstallManager};
+use astro_modintegrator::unreal_modloader::game_platform_managers::{SteamInstallManager, ProtonInstallManager};
 #[cfg(windows)]
 use astro_modintegrator::unreal_modloader::game_platform_managers::MsStoreInstallManager;
 use astro_modintegrator::unreal_modloader::update_info::UpdateInfo;
 use astro_modintegrator::unreal_modloader::version::GameBuild;
 use astro_modintegrator::{unreal_modloader, AstroIntegratorConfig};
 
+mod backup;
+mod changelog;
+mod cli;
+mod config_archive;
+mod game_build;
+mod gog;
+mod icon;
 mod logging;
+mod manual;
+mod mount_order;
+mod paths;
+mod scaffold;
+mod settings;
+mod steam;
+mod support;
+mod update_check;
+mod window;
 
 use autoupdater::apis::github::{GithubApi, GithubRelease};
 use autoupdater::apis::DownloadApiTrait;
 use autoupdater::cargo_crate_version;
-use log::info;
+use log::{error, info, warn};
 
-use lazy_static::lazy_static;
-
-#[derive(Debug, Default)]
-struct SteamGetGameBuild {
-    game_build: RefCell<Option<GameBuild>>,
-}
+use game_build::BuildVersionReader;
+use gog::GogInstallManager;
+use manual::ManualInstallManager;
+use settings::UpdateChannel;
 
-impl GetGameBuildTrait<SteamInstallManager> for SteamGetGameBuild {
-    fn get_game_build(&self, manager: &SteamInstallManager) -> Option<GameBuild> {
-        if self.game_build.borrow().is_none() && manager.get_game_install_path().is_some() {
-            let version_file_path = manager
-                .game_path
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .join("build.version");
-
-            if !version_file_path.is_file() {
-                info!("{:?} not found", version_file_path);
-                return None;
-            }
+use lazy_static::lazy_static;
 
-            let version_file = std::fs::read_to_string(&version_file_path).unwrap();
-            let game_build_string = version_file.split(' ').next().unwrap().to_owned();
+struct AstroGameConfig;
 
-            *self.game_build.borrow_mut() = GameBuild::try_from(&game_build_string).ok();
+fn load_icon() -> Option<IconData> {
+    let data = include_bytes!("../assets/icon.ico");
+    match icon::decode_icon(data) {
+        Ok(icon) => Some(icon),
+        Err(e) => {
+            error!("Failed to load the window icon: {}", e);
+            None
         }
-        *self.game_build.borrow()
     }
 }
 
-#[derive(Debug, Default)]
-struct ProtonGetGameBuild {
-    game_build: RefCell<Option<GameBuild>>,
+/// How often downloading an update is attempted before giving up
+const UPDATE_DOWNLOAD_ATTEMPTS: u32 = 3;
+/// Wait before retrying an update download, doubled after every failed attempt
+const UPDATE_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+lazy_static! {
+    static ref RGB_DATA: Option<IconData> = load_icon();
 }
 
-impl GetGameBuildTrait<ProtonInstallManager> for ProtonGetGameBuild {
-    fn get_game_build(&self, manager: &ProtonInstallManager) -> Option<GameBuild> {
-        if self.game_build.borrow().is_none() && manager.get_game_install_path().is_some() {
-            let version_file_path = manager
-                .game_path
-                .borrow()
-                .as_ref()
-                .unwrap()
-                .join("build.version");
-
-            if !version_file_path.is_file() {
-                info!("{:?} not found", version_file_path);
-                return None;
+impl AstroGameConfig {
+    fn get_api(&self) -> GithubApi {
+        let mut api = GithubApi::new("AstroTechies", "astro_modloader");
+        api.current_version(cargo_crate_version!());
+        api.prerelease(settings::SETTINGS.update_channel == UpdateChannel::Prerelease);
+        // unauthenticated requests are rate limited to 60 an hour
+        if let Ok(token) = std::env::var("GITHUB_TOKEN") {
+            if !token.is_empty() {
+                api.auth_token(&token);
             }
-
-            let version_file = std::fs::read_to_string(&version_file_path).unwrap();
-            let game_build_string = version_file.split(' ').next().unwrap().to_owned();
-
-            *self.game_build.borrow_mut() = GameBuild::try_from(&game_build_string).ok();
         }
-        *self.game_build.borrow()
+        api
     }
-}
 
-struct AstroGameConfig;
+    fn get_newer_release(
+        &self,
+        api: &GithubApi,
+    ) -> Result<Option<GithubRelease>, update_check::UpdateCheckError> {
+        Ok(api.get_newer(&None)?)
+    }
 
-fn load_icon() -> IconData {
-    let data = include_bytes!("../assets/icon.ico");
-    let image = image::load_from_memory(data).unwrap().to_rgba8();
+    /// Version and changelog of the pending update, readable without starting the download
+    pub(crate) fn get_update_changelog(
+        &self,
+    ) -> Result<Option<(String, String)>, ModLoaderError> {
+        if settings::SETTINGS.offline {
+            info!("Offline, not checking for updates");
+            return Ok(None);
+        }
 
-    IconData {
-        data: image.to_vec(),
-        width: image.width(),
-        height: image.height(),
-    }
-}
+        let api = self.get_api();
+        let download = self.get_newer_release(&api)?;
 
-lazy_static! {
-    static ref RGB_DATA: IconData = load_icon();
-}
+        Ok(download.map(|e| (e.tag_name, changelog::markdown_to_text(&e.body))))
+    }
 
-impl AstroGameConfig {
-    fn get_api(&self) -> GithubApi {
-        let mut api = GithubApi::new("AstroTechies", "astro_modloader");
-        api.current_version(cargo_crate_version!());
-        api.prerelease(true);
-        api
+    /// Restore the modloader version that was running before the last update
+    pub(crate) fn rollback_modloader(&self) -> Result<PathBuf, ModLoaderError> {
+        backup::restore_latest_backup().map_err(|e| ModLoaderError::other(e.to_string()))
     }
 
-    fn get_newer_release(&self, api: &GithubApi) -> Result<Option<GithubRelease>, ModLoaderError> {
-        api.get_newer(&None)
-            .map_err(|e| ModLoaderError::other(e.to_string()))
+    /// Install path found by every install manager, for users with more than one copy of the game
+    fn detect_install_paths(&self) -> BTreeMap<&'static str, Option<PathBuf>> {
+        let managers = <Self as GameConfig<
+            'static,
+            AstroIntegratorConfig,
+            (),
+            std::io::Error,
+        >>::get_install_managers(self);
+
+        managers
+            .into_iter()
+            .map(|(name, manager)| (name, manager.get_game_install_path()))
+            .collect()
     }
 }
 
@@ -121,20 +134,30 @@ where
     }
 
     fn get_game_build(&self, install_path: &Path) -> Option<GameBuild> {
-        let version_file_path = install_path.join("build.version");
-        if !version_file_path.is_file() {
-            info!("{:?} not found", version_file_path);
-            return None;
+        let missing_paks = paths::missing_core_paks(install_path);
+        if !missing_paks.is_empty() {
+            error!(
+                "{:?} doesn't look like an Astroneer install, missing {}",
+                install_path,
+                missing_paks.join(", ")
+            );
         }
 
-        let version_file = std::fs::read_to_string(&version_file_path).unwrap();
-        let game_build_string = version_file.split(' ').next().unwrap().to_owned();
+        let iostore_containers = paths::iostore_containers(install_path);
+        if !iostore_containers.is_empty() {
+            error!(
+                "This Astroneer build ships its content in IoStore containers ({}), which the modloader can't read yet. Mods will fail to integrate until IoStore support is added.",
+                iostore_containers.join(", ")
+            );
+        }
 
-        GameBuild::try_from(&game_build_string).ok()
+        let build_name = game_build::read_build_name(install_path);
+        window::show_game_build(build_name.as_deref());
+        build_name.and_then(|e| game_build::checked_game_build(&e))
     }
 
-    const WINDOW_TITLE: &'static str = "Astroneer Modloader";
-    const CONFIG_DIR: &'static str = "AstroModLoader";
+    const WINDOW_TITLE: &'static str = window::WINDOW_TITLE;
+    const CONFIG_DIR: &'static str = paths::CONFIG_DIR_NAME;
     const CRATE_VERSION: &'static str = cargo_crate_version!();
 
     fn get_install_managers(
@@ -143,24 +166,55 @@ where
         let mut managers: std::collections::BTreeMap<&'static str, Box<dyn InstallManager>> =
             BTreeMap::new();
 
+        // the managers only look in the default Steam library, so point them at
+        // the library that actually holds the game
+        let steam_install = steam::find_app_install(steam::ASTRONEER_APP_ID);
+
         #[cfg(not(target_os = "linux"))]
-        managers.insert(
-            "Steam",
-            Box::new(SteamInstallManager::new(
-                361420,
+        {
+            let manager = SteamInstallManager::new(
+                steam::ASTRONEER_APP_ID,
                 AstroIntegratorConfig::GAME_NAME,
-                Box::new(SteamGetGameBuild::default()),
-            )),
-        );
+                Box::new(BuildVersionReader::<SteamInstallManager>::default()),
+            );
+            if steam_install.is_some() {
+                *manager.game_path.borrow_mut() = steam_install.clone();
+            }
+            managers.insert("Steam", Box::new(manager));
+        }
         #[cfg(target_os = "linux")]
-        managers.insert(
-            "Steam (Proton)",
-            Box::new(ProtonInstallManager::new(
-                361420,
+        {
+            let manager = ProtonInstallManager::new(
+                steam::ASTRONEER_APP_ID,
                 AstroIntegratorConfig::GAME_NAME,
-                Box::new(ProtonGetGameBuild::default()),
-            ))
+                Box::new(BuildVersionReader::<ProtonInstallManager>::default()),
+            );
+            if steam_install.is_some() {
+                *manager.game_path.borrow_mut() = steam_install.clone();
+            }
+            managers.insert(
+                "Steam (Proton)",
+                Box::new(steam::ProtonPrefixInstallManager::new(
+                    manager,
+                    steam::ASTRONEER_APP_ID,
+                    AstroIntegratorConfig::GAME_NAME,
+                )),
+            );
+        }
+        managers.insert(
+            "GOG",
+            Box::new(GogInstallManager::new(Box::new(
+                BuildVersionReader::<GogInstallManager>::default(),
+            ))),
         );
+        if let Some(game_path) = settings::SETTINGS.game_path.clone() {
+            if let Some(manager) = ManualInstallManager::new(
+                game_path,
+                Box::new(BuildVersionReader::<ManualInstallManager>::default()),
+            ) {
+                managers.insert("Custom", Box::new(manager));
+            }
+        }
         #[cfg(windows)]
         managers.insert(
             "Microsoft Store",
@@ -174,37 +228,113 @@ where
     }
 
     fn get_newer_update(&self) -> Result<Option<UpdateInfo>, ModLoaderError> {
-        let api = self.get_api();
-        let download = self.get_newer_release(&api)?;
-
-        if let Some(download) = download {
-            return Ok(Some(UpdateInfo::new(download.tag_name, download.body)));
-        }
+        let changelog = self.get_update_changelog()?;
 
-        Ok(None)
+        Ok(changelog.map(|(version, changelog)| UpdateInfo::new(version, changelog)))
     }
 
     fn update_modloader(&self, callback: Box<dyn Fn(f32)>) -> Result<(), ModLoaderError> {
+        if settings::SETTINGS.offline {
+            return Ok(());
+        }
+
         let api = self.get_api();
         let download = self.get_newer_release(&api)?;
 
         if let Some(download) = download {
-            let asset = &download.assets[0];
-            api.download(asset, Some(callback))
-                .map_err(|e| ModLoaderError::other(e.to_string()))?;
+            let asset = download
+                .assets
+                .iter()
+                .filter_map(|e| {
+                    update_check::release_asset_rank(&e.name, std::env::consts::OS)
+                        .map(|rank| (rank, e))
+                })
+                .max_by_key(|(rank, _)| *rank)
+                .map(|(_, e)| e)
+                .ok_or_else(|| {
+                    ModLoaderError::other(format!(
+                        "Release {} has no build for {}",
+                        download.tag_name,
+                        std::env::consts::OS
+                    ))
+                })?;
+
+            let staged_backup = match backup::stage_current_exe(cargo_crate_version!()) {
+                Ok(staged_backup) => Some(staged_backup),
+                Err(e) => {
+                    warn!("Failed to back up the modloader, rolling back won't be possible: {}", e);
+                    None
+                }
+            };
+
+            // the download can't resume, every attempt starts over
+            let callback: Rc<dyn Fn(f32)> = Rc::from(callback);
+            let mut attempt = 1;
+            let mut delay = UPDATE_RETRY_DELAY;
+            loop {
+                let callback = Rc::clone(&callback);
+                match api.download(asset, Some(Box::new(move |progress| callback(progress)))) {
+                    Ok(_) => break,
+                    Err(e) if attempt < UPDATE_DOWNLOAD_ATTEMPTS => {
+                        warn!(
+                            "Failed to download the update: {}, retrying in {:?} ({}/{})",
+                            e, delay, attempt, UPDATE_DOWNLOAD_ATTEMPTS
+                        );
+                        thread::sleep(delay);
+                        attempt += 1;
+                        delay *= 2;
+                    }
+                    Err(e) => {
+                        if let Some(staged_backup) = staged_backup {
+                            staged_backup.discard();
+                        }
+                        return Err(ModLoaderError::other(e.to_string()));
+                    }
+                }
+            }
+
+            if let Some(staged_backup) = staged_backup {
+                staged_backup
+                    .commit()
+                    .map_err(|e| ModLoaderError::other(e.to_string()))?;
+            }
         }
         Ok(())
     }
 
     fn get_icon(&self) -> Option<IconData> {
-        Some(RGB_DATA.clone())
+        RGB_DATA.clone()
     }
 }
 
 fn main() {
+    // the log, the commands and backups all live in the config directory
+    let config_dir = match paths::ensure_config_dir() {
+        Ok(config_dir) => config_dir,
+        Err(e) => {
+            // the log falls back to the working directory without it
+            logging::init().unwrap();
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let subdir_errors = paths::ensure_config_subdirs(&config_dir);
+
+    // before anything, commands included, reads the integrator's settings
+    astro_modintegrator::settings::configure(settings::integrator_settings());
+
+    // commands run before logging is set up, as that truncates the previous log
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(exit_code) = cli::run_command(&args) {
+        std::process::exit(exit_code);
+    }
+
     logging::init().unwrap();
 
     info!("Astroneer Modloader");
+    for e in subdir_errors {
+        warn!("{}", e);
+    }
 
     let config = AstroGameConfig;
 
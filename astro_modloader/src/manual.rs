@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+
+use astro_modintegrator::unreal_modloader::config::InstallManager;
+use astro_modintegrator::unreal_modloader::error::ModLoaderError;
+use astro_modintegrator::unreal_modloader::game_platform_managers::GetGameBuildTrait;
+use astro_modintegrator::unreal_modloader::version::GameBuild;
+use log::warn;
+
+use crate::paths;
+
+/// Check that a folder looks like the root of an install, with the game executable or `build.version`
+fn is_install(install_path: &Path) -> bool {
+    install_path.join(paths::GAME_EXECUTABLE).is_file()
+        || install_path.join("build.version").is_file()
+}
+
+/// An install at a path the user gave, for copies of the game no platform knows about
+#[derive(Debug)]
+pub struct ManualInstallManager {
+    pub game_path: PathBuf,
+    get_game_build: Box<dyn GetGameBuildTrait<ManualInstallManager>>,
+}
+
+impl ManualInstallManager {
+    /// Returns `None`, with a warning, if `game_path` isn't an install
+    pub fn new(
+        game_path: PathBuf,
+        get_game_build: Box<dyn GetGameBuildTrait<ManualInstallManager>>,
+    ) -> Option<Self> {
+        if !is_install(&game_path) {
+            warn!(
+                "Ignoring the configured game path {:?}, it contains neither {} nor build.version",
+                game_path,
+                paths::GAME_EXECUTABLE
+            );
+            return None;
+        }
+
+        Some(ManualInstallManager {
+            game_path,
+            get_game_build,
+        })
+    }
+}
+
+impl InstallManager for ManualInstallManager {
+    fn get_game_install_path(&self) -> Option<PathBuf> {
+        Some(self.game_path.clone())
+    }
+
+    fn get_paks_path(&self) -> Option<PathBuf> {
+        paths::saved_paks_dir()
+    }
+
+    fn get_game_build(&self) -> Option<GameBuild> {
+        self.get_game_build.get_game_build(self)
+    }
+
+    fn launch_game(&self) -> Result<(), ModLoaderError> {
+        paths::launch_executable(&self.game_path).map_err(|e| ModLoaderError::other(e.to_string()))
+    }
+}
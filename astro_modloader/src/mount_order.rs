@@ -0,0 +1,186 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File name of the pak the integrator writes
+pub const INTEGRATED_PAK_NAME: &str = "999-Mods_P.pak";
+
+/// Base priority of paks shipped with the game, in `Astro/Content/Paks`
+const GAME_PAKS_ORDER: i32 = 4;
+/// Base priority of paks in the game's saved folder, where mods are installed
+const SAVED_PAKS_ORDER: i32 = 1;
+/// Priority added to patch paks, those ending in `_P.pak`
+const PATCH_PAK_ORDER: i32 = 100;
+
+/// A pak the game will mount, with the priority the engine gives it
+#[derive(Debug, Clone)]
+pub struct MountedPak {
+    pub path: PathBuf,
+    pub order: i32,
+}
+
+fn pak_order(file_name: &str, base_order: i32) -> i32 {
+    match file_name.ends_with("_P.pak") {
+        true => base_order + PATCH_PAK_ORDER,
+        false => base_order,
+    }
+}
+
+fn list_paks(dir: &Path, base_order: i32) -> Result<Vec<MountedPak>, io::Error> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut paks = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_name = match path.file_name().and_then(|e| e.to_str()) {
+            Some(file_name) if file_name.ends_with(".pak") => file_name.to_string(),
+            _ => continue,
+        };
+        paks.push(MountedPak {
+            order: pak_order(&file_name, base_order),
+            path,
+        });
+    }
+    Ok(paks)
+}
+
+/// Compute the order the game mounts its paks in, from lowest to highest priority.
+///
+/// This follows the engine's rules: saved folder paks start below the game's own,
+/// `_P` paks are raised above both, and ties are broken by file name
+/// with the later pak overriding the earlier ones.
+pub fn mount_order(
+    game_paks_dir: &Path,
+    mod_paks_dir: &Path,
+) -> Result<Vec<MountedPak>, io::Error> {
+    let mut paks = list_paks(game_paks_dir, GAME_PAKS_ORDER)?;
+    paks.extend(list_paks(mod_paks_dir, SAVED_PAKS_ORDER)?);
+    sort_paks(&mut paks);
+    Ok(paks)
+}
+
+/// The paks the game ships with in mount order, see [`mount_order`]
+pub fn game_paks(game_paks_dir: &Path) -> Result<Vec<MountedPak>, io::Error> {
+    let mut paks = list_paks(game_paks_dir, GAME_PAKS_ORDER)?;
+    sort_paks(&mut paks);
+    Ok(paks)
+}
+
+fn sort_paks(paks: &mut [MountedPak]) {
+    paks.sort_by(|a, b| {
+        a.order
+            .cmp(&b.order)
+            .then_with(|| a.path.file_name().cmp(&b.path.file_name()))
+    });
+}
+
+/// Describe the mount order, pointing out whether the integrated pak overrides everything else
+pub fn describe_mount_order(paks: &[MountedPak], integrated_pak_name: &str) -> String {
+    let mut description = String::from("Paks in mount order, later paks override earlier ones:\n");
+    for (i, pak) in paks.iter().enumerate() {
+        description.push_str(&format!(
+            "{:>3}. [{}] {}\n",
+            i + 1,
+            pak.order,
+            pak.path.display()
+        ));
+    }
+
+    let integrated_index = paks.iter().position(|e| {
+        e.path
+            .file_name()
+            .map(|e| e == integrated_pak_name)
+            .unwrap_or(false)
+    });
+    match integrated_index {
+        Some(index) if index + 1 == paks.len() => {
+            description.push_str(&format!("{} overrides all other paks", integrated_pak_name))
+        }
+        Some(index) => {
+            let overriding: Vec<String> = paks[index + 1..]
+                .iter()
+                .filter_map(|e| e.path.file_name())
+                .map(|e| e.to_string_lossy().to_string())
+                .collect();
+            description.push_str(&format!(
+                "{} is overridden by {}",
+                integrated_pak_name,
+                overriding.join(", ")
+            ))
+        }
+        None => description.push_str(&format!("{} was not found", integrated_pak_name)),
+    }
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::{describe_mount_order, mount_order, MountedPak, INTEGRATED_PAK_NAME};
+
+    #[test]
+    fn patch_paks_mount_above_the_game_and_ties_go_by_name() {
+        let dir = std::env::temp_dir().join(format!("astro_mount_order_{}", std::process::id()));
+        let game_paks_dir = dir.join("game");
+        let mod_paks_dir = dir.join("saved");
+        fs::create_dir_all(&game_paks_dir).unwrap();
+        fs::create_dir_all(&mod_paks_dir).unwrap();
+        for path in [
+            game_paks_dir.join("Astro-WindowsNoEditor.pak"),
+            game_paks_dir.join("Patch_P.pak"),
+            mod_paks_dir.join(INTEGRATED_PAK_NAME),
+            mod_paks_dir.join("001-SomeMod-1.0.0_P.pak"),
+            mod_paks_dir.join("readme.txt"),
+        ] {
+            fs::write(path, b"").unwrap();
+        }
+
+        let paks = mount_order(&game_paks_dir, &mod_paks_dir);
+        fs::remove_dir_all(&dir).unwrap();
+        let paks = paks.unwrap();
+
+        let order: Vec<(String, i32)> = paks
+            .iter()
+            .map(|e| {
+                (
+                    e.path.file_name().unwrap().to_string_lossy().to_string(),
+                    e.order,
+                )
+            })
+            .collect();
+        assert_eq!(
+            order,
+            [
+                (String::from("Astro-WindowsNoEditor.pak"), 4),
+                (String::from("001-SomeMod-1.0.0_P.pak"), 101),
+                (String::from(INTEGRATED_PAK_NAME), 101),
+                (String::from("Patch_P.pak"), 104),
+            ]
+        );
+        assert!(describe_mount_order(&paks, INTEGRATED_PAK_NAME)
+            .ends_with("999-Mods_P.pak is overridden by Patch_P.pak"));
+    }
+
+    #[test]
+    fn the_integrated_pak_is_reported_when_it_overrides_everything() {
+        let pak = |name: &str, order: i32| MountedPak {
+            path: PathBuf::from(name),
+            order,
+        };
+
+        let paks = [
+            pak("Astro-WindowsNoEditor.pak", 4),
+            pak(INTEGRATED_PAK_NAME, 101),
+        ];
+        let description = describe_mount_order(&paks, INTEGRATED_PAK_NAME);
+        assert!(description.contains("  2. [101] 999-Mods_P.pak\n"));
+        assert!(description.ends_with("999-Mods_P.pak overrides all other paks"));
+
+        assert!(describe_mount_order(&paks[..1], INTEGRATED_PAK_NAME)
+            .ends_with("999-Mods_P.pak was not found"));
+    }
+}
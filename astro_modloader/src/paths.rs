@@ -0,0 +1,154 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use astro_modintegrator::unreal_modintegrator::IntegratorConfig;
+use astro_modintegrator::AstroIntegratorConfig;
+
+use crate::settings::SETTINGS;
+#[cfg(target_os = "linux")]
+use crate::steam;
+
+/// Name of the modloader's folder in the platform's local data directory
+pub const CONFIG_DIR_NAME: &str = "AstroModLoader";
+
+/// Location of the modloader's config directory, `None` if the platform has no data directory
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|e| e.join(CONFIG_DIR_NAME))
+}
+
+/// Maps list in the config directory replacing the integrator's default maps
+pub const MAPS_FILE_NAME: &str = "maps.json";
+
+/// Maps list in the config directory, if the user made one
+pub fn config_maps_file() -> Option<PathBuf> {
+    config_dir()
+        .map(|e| e.join(MAPS_FILE_NAME))
+        .filter(|e| e.is_file())
+}
+
+/// Create a directory and its parents, with an error message users can act on
+pub fn create_dir(path: &Path) -> Result<(), io::Error> {
+    fs::create_dir_all(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!(
+                "Failed to create {:?}: {}. Check that the folder isn't read-only, \
+                 the disk isn't full and your user is allowed to write there.",
+                path, e
+            ),
+        )
+    })
+}
+
+/// Folder in the config directory holding the modloader's log
+pub const LOGS_DIR_NAME: &str = "logs";
+/// Folder in the config directory holding copies of previous modloader versions
+pub const BACKUPS_DIR_NAME: &str = "backups";
+
+/// Make sure the config directory exists, this has to succeed before anything else runs
+pub fn ensure_config_dir() -> Result<PathBuf, io::Error> {
+    let config_dir = config_dir().ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::NotFound,
+            "Could not determine the local data directory, check that your user profile is set up correctly",
+        )
+    })?;
+
+    create_dir(&config_dir)?;
+    Ok(config_dir)
+}
+
+/// Create the folders in the config directory. The modloader works without them,
+/// so the failures are returned to be logged instead of stopping it.
+pub fn ensure_config_subdirs(config_dir: &Path) -> Vec<io::Error> {
+    [LOGS_DIR_NAME, BACKUPS_DIR_NAME]
+        .iter()
+        .filter_map(|e| create_dir(&config_dir.join(e)).err())
+        .collect()
+}
+
+/// Executable in the root of an install
+pub const GAME_EXECUTABLE: &str = "Astro.exe";
+
+/// Start the game straight from its executable, for installs without a launcher to go through
+pub fn launch_executable(install_path: &Path) -> Result<(), io::Error> {
+    if !cfg!(windows) {
+        return Err(io::Error::new(
+            ErrorKind::Unsupported,
+            "This install can only be launched directly on Windows, start it from the launcher you installed it with",
+        ));
+    }
+
+    std::process::Command::new(install_path.join(GAME_EXECUTABLE))
+        .current_dir(install_path)
+        .spawn()?;
+    Ok(())
+}
+
+/// Folder the game loads paks from, relative to the install directory
+pub fn paks_dir(install_path: &Path) -> PathBuf {
+    install_path.join("Astro").join("Content").join("Paks")
+}
+
+/// Folder mods are installed to, in the game's saved data
+#[cfg(not(target_os = "linux"))]
+pub fn saved_paks_dir() -> Option<PathBuf> {
+    local_saved_paks_dir()
+}
+
+/// Folder mods are installed to, in the game's saved data.
+///
+/// The game runs under Proton, so that's inside its prefix, the same folder
+/// [`steam::ProtonPrefixInstallManager`] installs mods to.
+#[cfg(target_os = "linux")]
+pub fn saved_paks_dir() -> Option<PathBuf> {
+    match steam::find_compat_prefix(steam::ASTRONEER_APP_ID) {
+        Some(prefix) => Some(steam::prefix_saved_paks_dir(
+            &prefix,
+            AstroIntegratorConfig::GAME_NAME,
+        )),
+        // installs outside of Steam keep their saved data where Windows would
+        None => local_saved_paks_dir(),
+    }
+}
+
+fn local_saved_paks_dir() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|e| {
+        e.join(AstroIntegratorConfig::GAME_NAME)
+            .join("Saved")
+            .join("Paks")
+    })
+}
+
+/// IoStore containers in an install, the modloader can only read classic paks
+pub fn iostore_containers(install_path: &Path) -> Vec<String> {
+    let entries = match fs::read_dir(paks_dir(install_path)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut containers: Vec<String> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|e| {
+            e.extension()
+                .map(|e| e == "utoc" || e == "ucas")
+                .unwrap_or(false)
+        })
+        .filter_map(|e| e.file_name().map(|e| e.to_string_lossy().to_string()))
+        .collect();
+    containers.sort();
+    containers
+}
+
+/// Core game paks missing from an install, catches pointing the modloader at the wrong folder
+pub fn missing_core_paks(install_path: &Path) -> Vec<String> {
+    let paks_dir = paks_dir(install_path);
+    SETTINGS
+        .core_paks
+        .iter()
+        .filter(|e| !paks_dir.join(e).is_file())
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,80 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use astro_modintegrator::schema::validate_integrator_metadata;
+use astro_modintegrator::unreal_modintegrator::IntegratorConfig;
+use astro_modintegrator::AstroIntegratorConfig;
+use serde_json::json;
+
+const README: &str = "\
+This folder was generated by astro_modloader --scaffold-mod.
+
+metadata.json shows every integrator handler with an example entry:
+
+- persistent_actors: actors spawned once in every map
+- mission_trailheads: mission data assets added to the mission list
+- linked_actor_components: components added to an existing actor,
+  keyed by the actor's path
+- item_list_entries: entries appended to arrays of an existing asset,
+  keyed by the asset's path and then by the array name.
+  Use ExportName.ArrayName to pick an array on a specific export.
+
+Replace the /Game/MyMod/... paths with your own assets and delete the
+handlers your mod doesn't need. Pack this folder's contents together with
+your Astro/Content files into 000-<ModId>-<version>_P.pak.
+";
+
+fn scaffold_metadata(mod_id: &str) -> serde_json::Value {
+    json!({
+        "schema_version": 2,
+        "name": mod_id,
+        "mod_id": mod_id,
+        "author": "",
+        "description": "",
+        "version": "0.1.0",
+        "sync": "serverclient",
+        "integrator_version": AstroIntegratorConfig::INTEGRATOR_VERSION,
+        "integrator": {
+            "persistent_actors": ["/Game/MyMod/MyActor"],
+            "mission_trailheads": ["/Game/MyMod/MyMissionTrailhead"],
+            "linked_actor_components": {
+                "/Game/Globals/PlayControllerInstance": ["/Game/MyMod/MyComponent"]
+            },
+            "item_list_entries": {
+                "/Game/Items/BackpackRail": {
+                    "PrinterComponent.Blueprints": ["/Game/MyMod/MyItem"]
+                }
+            }
+        }
+    })
+}
+
+/// Write a minimal mod folder with example metadata for every handler
+pub fn scaffold_mod(mod_id: &str, output: &Path) -> Result<PathBuf, io::Error> {
+    if mod_id.is_empty() || !mod_id.chars().all(|e| e.is_ascii_alphanumeric()) {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "Mod ids may only contain letters and digits",
+        ));
+    }
+
+    let metadata = scaffold_metadata(mod_id);
+    let problems = validate_integrator_metadata(&metadata["integrator"]);
+    if !problems.is_empty() {
+        return Err(io::Error::new(
+            ErrorKind::Other,
+            format!("Generated metadata is invalid: {}", problems.join("; ")),
+        ));
+    }
+
+    let mod_dir = output.join(mod_id);
+    fs::create_dir_all(&mod_dir)?;
+    fs::write(
+        mod_dir.join("metadata.json"),
+        serde_json::to_string_pretty(&metadata)?,
+    )?;
+    fs::write(mod_dir.join("README.txt"), README)?;
+
+    Ok(mod_dir)
+}
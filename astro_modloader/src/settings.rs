@@ -0,0 +1,150 @@
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use astro_modintegrator::settings::{env_flag, env_list, env_or, IntegratorSettings};
+use lazy_static::lazy_static;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::paths;
+
+pub const SETTINGS_FILE_NAME: &str = "loader_settings.json";
+
+lazy_static! {
+    pub static ref SETTINGS: LoaderSettings = LoaderSettings::load();
+}
+
+/// Which releases the modloader updates to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    /// Only tagged stable releases
+    Stable,
+    /// Prereleases as well as stable releases
+    Prerelease,
+}
+
+impl FromStr for UpdateChannel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "stable" => Ok(UpdateChannel::Stable),
+            "prerelease" => Ok(UpdateChannel::Prerelease),
+            _ => Err(format!("Unknown update channel {}", s)),
+        }
+    }
+}
+
+/// Modloader settings, read from `loader_settings.json` in the config directory.
+///
+/// Every setting can also be overridden with an `ASTRO_*` environment variable.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LoaderSettings {
+    /// Paks that have to be present in the game's Paks folder
+    pub core_paks: Vec<String>,
+    /// Game builds known to break integration, like `1.24.29.0`
+    pub broken_builds: Vec<String>,
+    /// Refuse to mod a known broken build instead of only warning
+    pub refuse_broken_builds: bool,
+    /// Install folder of a copy of the game none of the platforms know about
+    pub game_path: Option<PathBuf>,
+    /// Releases to offer as updates
+    pub update_channel: UpdateChannel,
+    /// Never contact GitHub, for playing without a connection
+    pub offline: bool,
+    /// Title of the modloader window, the detected game build is appended to it
+    pub window_title: Option<String>,
+    /// GOG product id of Astroneer, GOG installs are matched by it
+    pub gog_game_id: Option<String>,
+    /// Fail integration when mod metadata doesn't match the metadata schema
+    pub strict_metadata: bool,
+    /// Also integrate development maps players never load
+    pub include_test_maps: bool,
+    /// Also integrate the tutorial map, for mods that should work in the tutorial.
+    /// Off by default, as every handler editing maps then has to read and write the
+    /// tutorial as well, which noticeably slows down integration.
+    pub include_tutorial_map: bool,
+}
+
+impl Default for LoaderSettings {
+    fn default() -> Self {
+        LoaderSettings {
+            core_paks: Vec::from([String::from("Astro-WindowsNoEditor.pak")]),
+            broken_builds: Vec::new(),
+            refuse_broken_builds: false,
+            game_path: None,
+            update_channel: UpdateChannel::Stable,
+            offline: false,
+            window_title: None,
+            gog_game_id: None,
+            strict_metadata: false,
+            include_test_maps: false,
+            include_tutorial_map: false,
+        }
+    }
+}
+
+impl LoaderSettings {
+    fn from_file() -> Option<Self> {
+        let path = paths::config_dir()?.join(SETTINGS_FILE_NAME);
+        let data = fs::read_to_string(&path).ok()?;
+
+        match serde_json::from_str(&data) {
+            Ok(settings) => Some(settings),
+            Err(e) => {
+                warn!("Ignoring invalid {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn load() -> Self {
+        let settings = LoaderSettings::from_file().unwrap_or_default();
+
+        LoaderSettings {
+            core_paks: env_list("ASTRO_CORE_PAKS", settings.core_paks),
+            broken_builds: env_list("ASTRO_BROKEN_BUILDS", settings.broken_builds),
+            refuse_broken_builds: env_flag(
+                "ASTRO_REFUSE_BROKEN_BUILDS",
+                settings.refuse_broken_builds,
+            ),
+            game_path: std::env::var_os("ASTRO_GAME_PATH")
+                .map(PathBuf::from)
+                .or(settings.game_path),
+            update_channel: env_or("ASTRO_UPDATE_CHANNEL", settings.update_channel),
+            offline: env_flag("ASTRO_OFFLINE", settings.offline),
+            window_title: std::env::var("ASTRO_WINDOW_TITLE")
+                .ok()
+                .or(settings.window_title),
+            gog_game_id: std::env::var("ASTRO_GOG_GAME_ID")
+                .ok()
+                .or(settings.gog_game_id),
+            strict_metadata: env_flag("ASTRO_STRICT_METADATA", settings.strict_metadata),
+            include_test_maps: env_flag("ASTRO_INCLUDE_TEST_MAPS", settings.include_test_maps),
+            include_tutorial_map: env_flag(
+                "ASTRO_INCLUDE_TUTORIAL_MAP",
+                settings.include_tutorial_map,
+            ),
+        }
+    }
+}
+
+/// Integrator settings from the environment and `loader_settings.json`, with the maps
+/// list and change log folder pointed at the modloader's folders unless the environment chose them
+pub fn integrator_settings() -> IntegratorSettings {
+    let mut settings = IntegratorSettings::from_env();
+    settings.strict_metadata = SETTINGS.strict_metadata;
+    settings.include_test_maps = SETTINGS.include_test_maps;
+    settings.include_tutorial_map = SETTINGS.include_tutorial_map;
+    if settings.maps_file.is_none() {
+        settings.maps_file = paths::config_maps_file();
+    }
+    // the change log goes next to the integrated pak
+    if settings.change_log_dir.is_none() {
+        settings.change_log_dir = paths::saved_paks_dir();
+    }
+    settings
+}
@@ -0,0 +1,588 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "linux")]
+use astro_modintegrator::unreal_modloader::{
+    config::InstallManager, error::ModLoaderError, game_platform_managers::ProtonInstallManager,
+    version::GameBuild,
+};
+#[cfg(target_os = "linux")]
+use log::info;
+use log::{debug, warn};
+
+/// Steam app id of Astroneer
+pub const ASTRONEER_APP_ID: u32 = 361420;
+
+/// A value in one of Steam's text vdf (KeyValues) files
+#[derive(Debug, Clone)]
+pub enum VdfValue {
+    String(String),
+    Map(Vec<(String, VdfValue)>),
+}
+
+impl VdfValue {
+    /// Look up a key, Steam isn't consistent about key casing so this is case insensitive
+    pub fn get(&self, key: &str) -> Option<&VdfValue> {
+        match self {
+            VdfValue::Map(entries) => entries
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(key))
+                .map(|(_, value)| value),
+            VdfValue::String(_) => None,
+        }
+    }
+
+    pub fn get_path(&self, path: &[&str]) -> Option<&VdfValue> {
+        path.iter().try_fold(self, |value, key| value.get(key))
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            VdfValue::String(value) => Some(value),
+            VdfValue::Map(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    String(String),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => tokens.push(Token::Open),
+            '}' => tokens.push(Token::Close),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            'n' => value.push('\n'),
+                            't' => value.push('\t'),
+                            c => value.push(c),
+                        },
+                        c => value.push(c),
+                    }
+                }
+                tokens.push(Token::String(value));
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            _ => {
+                // unquoted token
+                let mut value = String::from(c);
+                while let Some(c) = chars.peek() {
+                    if c.is_whitespace() || *c == '{' || *c == '}' || *c == '"' {
+                        break;
+                    }
+                    value.push(*c);
+                    chars.next();
+                }
+                tokens.push(Token::String(value));
+            }
+        }
+    }
+
+    Some(tokens)
+}
+
+fn parse_entries(tokens: &mut std::vec::IntoIter<Token>, nested: bool) -> Option<VdfValue> {
+    let mut entries = Vec::new();
+    loop {
+        let key = match tokens.next() {
+            Some(Token::String(key)) => key,
+            Some(Token::Close) if nested => break,
+            None if !nested => break,
+            _ => return None,
+        };
+        let value = match tokens.next()? {
+            Token::String(value) => VdfValue::String(value),
+            Token::Open => parse_entries(tokens, true)?,
+            Token::Close => return None,
+        };
+        entries.push((key, value));
+    }
+    Some(VdfValue::Map(entries))
+}
+
+/// Parse a text vdf file, returns `None` if the file is malformed
+pub fn parse_vdf(text: &str) -> Option<VdfValue> {
+    let mut tokens = tokenize(text)?.into_iter();
+    parse_entries(&mut tokens, false)
+}
+
+pub fn read_vdf(path: &Path) -> Option<VdfValue> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) => {
+            debug!("Failed to read {:?}: {}", path, e);
+            return None;
+        }
+    };
+
+    let vdf = parse_vdf(&text);
+    if vdf.is_none() {
+        warn!("Failed to parse {:?}", path);
+    }
+    vdf
+}
+
+/// Possible Steam installation directories
+#[cfg(not(windows))]
+pub fn steam_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    if let Ok(home) = std::env::var("HOME") {
+        let home = PathBuf::from(home);
+        roots.push(home.join(".steam").join("steam"));
+        roots.push(home.join(".local").join("share").join("Steam"));
+        // sandboxed installs, only used when there's no native one
+        roots.push(
+            home.join(".var")
+                .join("app")
+                .join("com.valvesoftware.Steam")
+                .join(".local")
+                .join("share")
+                .join("Steam"),
+        );
+        roots.push(
+            home.join("snap")
+                .join("steam")
+                .join("common")
+                .join(".local")
+                .join("share")
+                .join("Steam"),
+        );
+    }
+
+    roots.retain(|e| e.is_dir());
+    roots
+}
+
+/// Possible Steam installation directories, the one Steam registered first
+#[cfg(windows)]
+pub fn steam_roots() -> Vec<PathBuf> {
+    use winreg::enums::HKEY_CURRENT_USER;
+    use winreg::RegKey;
+
+    let mut roots = Vec::new();
+
+    if let Ok(steam_path) = RegKey::predef(HKEY_CURRENT_USER)
+        .open_subkey("Software\\Valve\\Steam")
+        .and_then(|e| e.get_value::<String, _>("SteamPath"))
+    {
+        roots.push(PathBuf::from(steam_path));
+    }
+    if let Ok(program_files) = std::env::var("ProgramFiles(x86)") {
+        roots.push(PathBuf::from(program_files).join("Steam"));
+    }
+
+    roots.retain(|e| e.is_dir());
+    roots
+}
+
+/// Every library folder of a Steam installation, including the installation itself
+fn library_folders(root: &Path) -> Vec<PathBuf> {
+    let mut libraries = vec![root.to_path_buf()];
+
+    if let Some(folders) = read_vdf(&root.join("steamapps").join("libraryfolders.vdf")) {
+        for path in library_paths(&folders) {
+            if !libraries.contains(&path) {
+                libraries.push(path);
+            }
+        }
+    }
+    libraries
+}
+
+/// Library folders listed in a parsed `libraryfolders.vdf`
+fn library_paths(folders: &VdfValue) -> Vec<PathBuf> {
+    let folders = match folders.get("libraryfolders") {
+        Some(VdfValue::Map(folders)) => folders,
+        _ => return Vec::new(),
+    };
+
+    let mut paths = Vec::new();
+    for (key, folder) in folders {
+        // older Steam versions list paths directly, newer ones in a "path" entry
+        let path = match folder {
+            VdfValue::String(path) if key.parse::<u32>().is_ok() => Some(path.as_str()),
+            VdfValue::Map(_) => folder.get("path").and_then(|e| e.as_str()),
+            _ => None,
+        };
+        if let Some(path) = path {
+            paths.push(PathBuf::from(path));
+        }
+    }
+    paths
+}
+
+/// Find the folder an app is installed to, in whichever Steam library holds it
+pub fn find_app_install(app_id: u32) -> Option<PathBuf> {
+    for root in steam_roots() {
+        for library in library_folders(&root) {
+            let steamapps = library.join("steamapps");
+            let manifest = steamapps.join(format!("appmanifest_{}.acf", app_id));
+            if !manifest.is_file() {
+                continue;
+            }
+
+            let install_dir = match read_vdf(&manifest)
+                .and_then(|e| e.get_path(&["AppState", "installdir"]).cloned())
+            {
+                Some(VdfValue::String(install_dir)) => install_dir,
+                _ => continue,
+            };
+
+            let install_path = steamapps.join("common").join(install_dir);
+            if install_path.is_dir() {
+                debug!("Found app {} in Steam library {:?}", app_id, library);
+                return Some(install_path);
+            }
+        }
+    }
+    None
+}
+
+/// Variable pointing Proton at the data folder holding an app's prefix
+#[cfg(target_os = "linux")]
+const COMPAT_DATA_PATH_VARIABLE: &str = "STEAM_COMPAT_DATA_PATH";
+
+/// The `STEAM_COMPAT_DATA_PATH` set in an app's launch options,
+/// as in `STEAM_COMPAT_DATA_PATH=/games/astroneer %command%`
+#[cfg(target_os = "linux")]
+fn launch_options_compat_data_path(launch_options: &str) -> Option<PathBuf> {
+    let prefix = format!("{}=", COMPAT_DATA_PATH_VARIABLE);
+    let value = launch_options
+        .split_whitespace()
+        .take_while(|e| *e != "%command%")
+        .find_map(|e| e.strip_prefix(&prefix))?
+        .trim_matches(|c| c == '"' || c == '\'');
+    match value.is_empty() {
+        true => None,
+        false => Some(PathBuf::from(value)),
+    }
+}
+
+/// The compat data folder set in the launch options of an app, for any Steam user
+#[cfg(target_os = "linux")]
+fn launch_options_compat_data(app_id: u32) -> Option<PathBuf> {
+    let app_id = app_id.to_string();
+    for root in steam_roots() {
+        let users = match fs::read_dir(root.join("userdata")) {
+            Ok(users) => users,
+            Err(_) => continue,
+        };
+
+        for user in users.flatten() {
+            let config = match read_vdf(&user.path().join("config").join("localconfig.vdf")) {
+                Some(config) => config,
+                None => continue,
+            };
+            let launch_options = config
+                .get_path(&[
+                    "UserLocalConfigStore",
+                    "Software",
+                    "Valve",
+                    "Steam",
+                    "apps",
+                    app_id.as_str(),
+                    "LaunchOptions",
+                ])
+                .and_then(|e| e.as_str());
+
+            if let Some(compat_data) = launch_options.and_then(launch_options_compat_data_path) {
+                return Some(compat_data);
+            }
+        }
+    }
+    None
+}
+
+/// Find the Proton prefix of an app.
+///
+/// A `STEAM_COMPAT_DATA_PATH` in the environment or the app's launch options wins,
+/// otherwise the prefix is looked for in whichever Steam library holds the app.
+#[cfg(target_os = "linux")]
+pub fn find_compat_prefix(app_id: u32) -> Option<PathBuf> {
+    let custom_compat_data = std::env::var_os(COMPAT_DATA_PATH_VARIABLE)
+        .map(PathBuf::from)
+        .or_else(|| launch_options_compat_data(app_id));
+    if let Some(compat_data) = custom_compat_data {
+        let prefix = compat_data.join("pfx");
+        if prefix.is_dir() {
+            return Some(prefix);
+        }
+        warn!(
+            "{} is set to {:?}, which has no Proton prefix",
+            COMPAT_DATA_PATH_VARIABLE, compat_data
+        );
+    }
+
+    steam_roots()
+        .into_iter()
+        .flat_map(|e| library_folders(&e))
+        .map(|e| {
+            e.join("steamapps")
+                .join("compatdata")
+                .join(app_id.to_string())
+                .join("pfx")
+        })
+        .find(|e| e.is_dir())
+}
+
+/// Find the name of the compatibility tool (Proton version) Steam runs an app with.
+///
+/// Falls back to the tool selected for all games if the app has no override.
+#[cfg(target_os = "linux")]
+pub fn get_compat_tool(app_id: u32) -> Option<String> {
+    for root in steam_roots() {
+        let config = match read_vdf(&root.join("config").join("config.vdf")) {
+            Some(config) => config,
+            None => continue,
+        };
+
+        let mapping = match config.get_path(&[
+            "InstallConfigStore",
+            "Software",
+            "Valve",
+            "Steam",
+            "CompatToolMapping",
+        ]) {
+            Some(mapping) => mapping,
+            None => continue,
+        };
+
+        let tool = [app_id.to_string(), String::from("0")]
+            .iter()
+            .filter_map(|e| mapping.get(e))
+            .filter_map(|e| e.get("name").and_then(|e| e.as_str()))
+            .find(|e| !e.is_empty())
+            .map(String::from);
+
+        if tool.is_some() {
+            return tool;
+        }
+    }
+    None
+}
+
+/// Folder a game run by Proton loads mod paks from, in the game's saved data inside the prefix
+#[cfg(target_os = "linux")]
+pub fn prefix_saved_paks_dir(prefix: &Path, game_name: &str) -> PathBuf {
+    prefix
+        .join("drive_c")
+        .join("users")
+        .join("steamuser")
+        .join("AppData")
+        .join("Local")
+        .join(game_name)
+        .join("Saved")
+        .join("Paks")
+}
+
+/// The Proton manager, with its paks in the prefix of the library that holds the game.
+///
+/// The prefix is only looked for in the native Steam root otherwise,
+/// which misses Flatpak and Snap installs and games in other libraries.
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+pub struct ProtonPrefixInstallManager {
+    manager: ProtonInstallManager,
+    game_name: &'static str,
+    prefix: Option<PathBuf>,
+}
+
+#[cfg(target_os = "linux")]
+impl ProtonPrefixInstallManager {
+    pub fn new(manager: ProtonInstallManager, app_id: u32, game_name: &'static str) -> Self {
+        match get_compat_tool(app_id) {
+            Some(tool) => info!("{} is set to run with {}", game_name, tool),
+            None => info!("No compatibility tool configured for {}", game_name),
+        }
+
+        let prefix = find_compat_prefix(app_id);
+        if let Some(prefix) = &prefix {
+            debug!("Using Proton prefix {:?}", prefix);
+        }
+
+        ProtonPrefixInstallManager {
+            manager,
+            game_name,
+            prefix,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl InstallManager for ProtonPrefixInstallManager {
+    fn get_game_install_path(&self) -> Option<PathBuf> {
+        self.manager.get_game_install_path()
+    }
+
+    fn get_paks_path(&self) -> Option<PathBuf> {
+        match &self.prefix {
+            Some(prefix) => Some(prefix_saved_paks_dir(prefix, self.game_name)),
+            None => self.manager.get_paks_path(),
+        }
+    }
+
+    fn get_game_build(&self) -> Option<GameBuild> {
+        self.manager.get_game_build()
+    }
+
+    fn launch_game(&self) -> Result<(), ModLoaderError> {
+        self.manager.launch_game()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    #[cfg(target_os = "linux")]
+    use super::launch_options_compat_data_path;
+    use super::{library_paths, parse_vdf};
+
+    #[test]
+    fn quoted_strings_are_unescaped() {
+        let vdf = parse_vdf(
+            r#""LaunchOptions" "PROTON_LOG=1 \"%command%\"\t-dx11"
+            unquoted value // comment
+            "path" "C:\\Games\\Steam""#,
+        )
+        .unwrap();
+        assert_eq!(
+            vdf.get("LaunchOptions").and_then(|e| e.as_str()),
+            Some("PROTON_LOG=1 \"%command%\"\t-dx11")
+        );
+        assert_eq!(vdf.get("unquoted").and_then(|e| e.as_str()), Some("value"));
+        assert_eq!(
+            vdf.get("path").and_then(|e| e.as_str()),
+            Some("C:\\Games\\Steam")
+        );
+    }
+
+    #[test]
+    fn nested_blocks_are_looked_up_case_insensitively() {
+        let vdf = parse_vdf(
+            r#""AppState"
+            {
+                "appid" "361420"
+                "UserConfig"
+                {
+                    "language" "english"
+                }
+                "installdir" "ASTRONEER"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vdf.get_path(&["appstate", "InstallDir"])
+                .and_then(|e| e.as_str()),
+            Some("ASTRONEER")
+        );
+        assert_eq!(
+            vdf.get_path(&["AppState", "UserConfig", "language"])
+                .and_then(|e| e.as_str()),
+            Some("english")
+        );
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        assert!(parse_vdf(r#""AppState" { "appid" "361420""#).is_none());
+        assert!(parse_vdf(r#""AppState" }"#).is_none());
+        assert!(parse_vdf(r#""unterminated"#).is_none());
+    }
+
+    #[test]
+    fn flat_library_folders_are_listed() {
+        let vdf = parse_vdf(
+            r#""LibraryFolders"
+            {
+                "TimeNextStatsReport" "1650000000"
+                "ContentStatsID" "-1234"
+                "1" "/mnt/games/SteamLibrary"
+                "2" "/mnt/other/SteamLibrary"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            library_paths(&vdf),
+            Vec::from([
+                PathBuf::from("/mnt/games/SteamLibrary"),
+                PathBuf::from("/mnt/other/SteamLibrary"),
+            ])
+        );
+    }
+
+    #[test]
+    fn path_keyed_library_folders_are_listed() {
+        let vdf = parse_vdf(
+            r#""libraryfolders"
+            {
+                "contentstatsid" "-1234"
+                "0"
+                {
+                    "path" "/home/user/.local/share/Steam"
+                    "label" ""
+                    "apps"
+                    {
+                        "228980" "123"
+                    }
+                }
+                "1"
+                {
+                    "path" "/mnt/games/SteamLibrary"
+                    "apps"
+                    {
+                        "361420" "456"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            library_paths(&vdf),
+            Vec::from([
+                PathBuf::from("/home/user/.local/share/Steam"),
+                PathBuf::from("/mnt/games/SteamLibrary"),
+            ])
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn compat_data_path_is_read_from_launch_options() {
+        assert_eq!(
+            launch_options_compat_data_path("STEAM_COMPAT_DATA_PATH=/games/astroneer %command%"),
+            Some(PathBuf::from("/games/astroneer"))
+        );
+        assert_eq!(
+            launch_options_compat_data_path(
+                "PROTON_LOG=1 STEAM_COMPAT_DATA_PATH=\"/games/pfx\" %command%"
+            ),
+            Some(PathBuf::from("/games/pfx"))
+        );
+        assert_eq!(
+            launch_options_compat_data_path("%command% STEAM_COMPAT_DATA_PATH=/games/astroneer"),
+            None
+        );
+        assert_eq!(launch_options_compat_data_path("-dx11"), None);
+    }
+}
@@ -0,0 +1,275 @@
+use std::fs;
+use std::io::{self, prelude::*, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use astro_modintegrator::dump::list_pak_entries;
+use autoupdater::cargo_crate_version;
+use lazy_static::lazy_static;
+use regex::Regex;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::logging::LOG_FILE_NAME;
+use crate::{game_build, logging, mount_order, paths, AstroGameConfig};
+
+pub const SUPPORT_BUNDLE_FILE_NAME: &str = "astro_modloader_support.zip";
+pub const INSPECT_REPORT_FILE_NAME: &str = "astro_modloader_inspect.txt";
+
+/// Listing of the integrated pak's entries in the support bundle
+const INTEGRATED_PAK_MANIFEST_NAME: &str = "integrated_pak.txt";
+/// Number of lines from the end of the last log included in the inspect report
+const INSPECT_LOG_LINES: usize = 50;
+
+lazy_static! {
+    /// Home folders on Windows, Linux and macOS, backslashes can be escaped in debug output
+    static ref HOME_DIR_REGEX: Regex =
+        Regex::new(r#"(?i)([\\/]+(?:Users|home)[\\/]+)([^\\/\s"']+)"#).unwrap();
+}
+
+/// Replace `username` in the home folders of paths in `text`,
+/// so that paths like `C:\Users\<name>\...` don't end up in bug reports.
+///
+/// Only the folder name is replaced, the name anywhere else is left alone,
+/// short names would otherwise mangle unrelated words.
+fn scrub_home_dirs(text: &str, username: &str) -> String {
+    if username.is_empty() {
+        return text.to_string();
+    }
+    HOME_DIR_REGEX
+        .replace_all(text, |captures: &regex::Captures| {
+            match captures[2].eq_ignore_ascii_case(username) {
+                true => format!("{}<user>", &captures[1]),
+                false => captures[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+/// Replace the current user's name in the paths in `text`, see [`scrub_home_dirs`]
+fn scrub_username(text: &str) -> String {
+    let username = std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_default();
+    scrub_home_dirs(text, &username)
+}
+
+/// List the entries of the integrated pak, `None` if there is none
+fn describe_integrated_pak() -> Result<Option<String>, io::Error> {
+    let integrated_pak = match paths::saved_paks_dir() {
+        Some(mod_paks_dir) => mod_paks_dir.join(mount_order::INTEGRATED_PAK_NAME),
+        None => return Ok(None),
+    };
+
+    match list_pak_entries(&integrated_pak) {
+        Ok(entries) => Ok(Some(
+            entries
+                .iter()
+                .map(|(name, size)| format!("{:>10} {}\n", size, name))
+                .collect(),
+        )),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Ok(Some(format!("unreadable: {}\n", e))),
+    }
+}
+
+/// Bundle the log of the last run together with some basic information
+/// about the modloader into a single zip that can be attached to a bug report.
+///
+/// This has to run before logging is initialized, as that truncates the log file.
+pub fn collect_support_bundle(scrub_paths: bool) -> Result<PathBuf, io::Error> {
+    let map_zip_err = |e: zip::result::ZipError| io::Error::new(ErrorKind::Other, e);
+
+    let mut info = format!(
+        "modloader version: {}\nos: {}\narch: {}\n",
+        cargo_crate_version!(),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    for (manager, install_path) in AstroGameConfig.detect_install_paths() {
+        info.push_str(&format!("install ({}): {:?}\n", manager, install_path));
+    }
+    let info = match scrub_paths {
+        true => scrub_username(&info),
+        false => info,
+    };
+
+    let log = match fs::read_to_string(logging::log_file_path()) {
+        Ok(log) => Some(log),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    let bundle_path = PathBuf::from(SUPPORT_BUNDLE_FILE_NAME);
+    let mut zip = ZipWriter::new(fs::File::create(&bundle_path)?);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file("info.txt", options).map_err(map_zip_err)?;
+    zip.write_all(info.as_bytes())?;
+
+    if let Some(log) = log {
+        let log = match scrub_paths {
+            true => scrub_username(&log),
+            false => log,
+        };
+
+        zip.start_file(LOG_FILE_NAME, options)
+            .map_err(map_zip_err)?;
+        zip.write_all(log.as_bytes())?;
+    }
+
+    if let Some(integrated_pak) = describe_integrated_pak()? {
+        zip.start_file(INTEGRATED_PAK_MANIFEST_NAME, options)
+            .map_err(map_zip_err)?;
+        zip.write_all(integrated_pak.as_bytes())?;
+    }
+
+    if astro_modintegrator::settings::SETTINGS.change_log {
+        match fs::read_to_string(astro_modintegrator::change_log_path()) {
+            Ok(change_log) => {
+                let change_log = match scrub_paths {
+                    true => scrub_username(&change_log),
+                    false => change_log,
+                };
+
+                zip.start_file(astro_modintegrator::CHANGE_LOG_FILE_NAME, options)
+                    .map_err(map_zip_err)?;
+                zip.write_all(change_log.as_bytes())?;
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    zip.finish().map_err(map_zip_err)?;
+
+    Ok(bundle_path)
+}
+
+/// Describe the paks in the mods folder, each with its size
+fn describe_mod_paks(mod_paks_dir: &Path) -> String {
+    let entries = match fs::read_dir(mod_paks_dir) {
+        Ok(entries) => entries,
+        Err(e) => return format!("  can't read {:?}: {}\n", mod_paks_dir, e),
+    };
+
+    let mut paks: Vec<(String, u64)> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let file_name = e.file_name().to_string_lossy().to_string();
+            let size = e.metadata().ok()?.len();
+            file_name.ends_with(".pak").then_some((file_name, size))
+        })
+        .collect();
+    paks.sort();
+
+    if paks.is_empty() {
+        return String::from("  none\n");
+    }
+    paks.iter()
+        .map(|(file_name, size)| format!("  {:>10} {}\n", size, file_name))
+        .collect()
+}
+
+/// Gather every read-only diagnostic in one report for whoever is helping the user.
+///
+/// Nothing is written or changed while collecting it.
+pub fn inspect_report(scrub_paths: bool) -> String {
+    let mut report = format!(
+        "modloader version: {}\nos: {}\narch: {}\n\ninstalls:\n",
+        cargo_crate_version!(),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    for (manager, install_path) in AstroGameConfig.detect_install_paths() {
+        let install_path = match install_path {
+            Some(install_path) => install_path,
+            None => {
+                report.push_str(&format!("  {}: not found\n", manager));
+                continue;
+            }
+        };
+        let build = game_build::read_build_name(&install_path);
+        report.push_str(&format!(
+            "  {}: {:?}, build {}, {} IoStore containers\n",
+            manager,
+            install_path,
+            build.as_deref().unwrap_or("unknown"),
+            paths::iostore_containers(&install_path).len()
+        ));
+    }
+
+    match paths::saved_paks_dir() {
+        Some(mod_paks_dir) => {
+            report.push_str(&format!("\nmods in {:?}:\n", mod_paks_dir));
+            report.push_str(&describe_mod_paks(&mod_paks_dir));
+
+            let integrated_pak = mod_paks_dir.join(mount_order::INTEGRATED_PAK_NAME);
+            let integrated = match list_pak_entries(&integrated_pak) {
+                Ok(entries) => format!(
+                    "{} assets, {} bytes",
+                    entries.len(),
+                    entries.iter().map(|(_, size)| size).sum::<u64>()
+                ),
+                Err(e) if e.kind() == ErrorKind::NotFound => String::from("not found"),
+                Err(e) => format!("unreadable: {}", e),
+            };
+            report.push_str(&format!(
+                "\nintegrated pak {}: {}\n",
+                mount_order::INTEGRATED_PAK_NAME,
+                integrated
+            ));
+        }
+        None => report.push_str("\nmods: could not determine the local data directory\n"),
+    }
+
+    report.push_str(&format!(
+        "\nlast {} lines of {}:\n",
+        INSPECT_LOG_LINES, LOG_FILE_NAME
+    ));
+    match fs::read_to_string(logging::log_file_path()) {
+        Ok(log) => {
+            let lines: Vec<&str> = log.lines().collect();
+            let start = lines.len().saturating_sub(INSPECT_LOG_LINES);
+            for line in &lines[start..] {
+                report.push_str(line);
+                report.push('\n');
+            }
+        }
+        Err(e) => report.push_str(&format!("  can't read the log: {}\n", e)),
+    }
+
+    match scrub_paths {
+        true => scrub_username(&report),
+        false => report,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::scrub_home_dirs;
+
+    #[test]
+    fn only_home_folders_are_scrubbed() {
+        assert_eq!(
+            scrub_home_dirs(r"C:\Users\dev\AppData\Local", "dev"),
+            r"C:\Users\<user>\AppData\Local"
+        );
+        assert_eq!(
+            scrub_home_dirs(r#""C:\\Users\\Dev\\AppData""#, "dev"),
+            r#""C:\\Users\\<user>\\AppData""#
+        );
+        assert_eq!(
+            scrub_home_dirs("/home/dev/.local/share/Astro", "dev"),
+            "/home/<user>/.local/share/Astro"
+        );
+        assert_eq!(
+            scrub_home_dirs("developer dev mod in /Game/dev/DevItems", "dev"),
+            "developer dev mod in /Game/dev/DevItems"
+        );
+        assert_eq!(
+            scrub_home_dirs("/home/devon/mods", "dev"),
+            "/home/devon/mods"
+        );
+        assert_eq!(scrub_home_dirs("/home/dev", ""), "/home/dev");
+    }
+}
@@ -0,0 +1,120 @@
+use std::fmt;
+
+use astro_modintegrator::unreal_modloader::error::ModLoaderError;
+
+/// Name of the binary cargo builds, releases attach the builds under it
+const BINARY_NAME: &str = env!("CARGO_PKG_NAME");
+
+/// Header GitHub sends with every API response, `0` once the rate limit is used up
+const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+
+/// Why checking GitHub for a newer release failed
+#[derive(Debug)]
+pub enum UpdateCheckError {
+    /// GitHub refused the request because the rate limit was reached
+    RateLimited { status: u16 },
+    /// Any other failure, e.g. no connection
+    Failed(String),
+}
+
+impl From<autoupdater::Error> for UpdateCheckError {
+    fn from(error: autoupdater::Error) -> Self {
+        if let autoupdater::Error::Http(http_error) = &error {
+            if let ureq::Error::Status(status, response) = http_error.as_ref() {
+                let remaining = response.header(RATE_LIMIT_REMAINING_HEADER);
+                if is_rate_limited(*status, remaining) {
+                    return UpdateCheckError::RateLimited { status: *status };
+                }
+            }
+        }
+        UpdateCheckError::Failed(error.to_string())
+    }
+}
+
+impl fmt::Display for UpdateCheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateCheckError::RateLimited { status } => write!(
+                f,
+                "GitHub's rate limit was reached (HTTP {}), try again later or set GITHUB_TOKEN",
+                status
+            ),
+            UpdateCheckError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for UpdateCheckError {}
+
+impl From<UpdateCheckError> for ModLoaderError {
+    fn from(error: UpdateCheckError) -> Self {
+        ModLoaderError::other(error.to_string())
+    }
+}
+
+/// GitHub answers 429 when rate limited, or 403 with no requests remaining.
+/// Other 403s are permission problems, e.g. a token without access.
+fn is_rate_limited(status: u16, remaining: Option<&str>) -> bool {
+    match status {
+        429 => true,
+        403 => remaining.map(|e| e.trim() == "0").unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// How well a release asset matches the platform `os`, `None` if it isn't a build for it.
+///
+/// The build is `astro_modloader.exe` on Windows and `astro_modloader` everywhere else,
+/// assets named after the build and the platform, like `astro_modloader-linux`, rank below it.
+pub fn release_asset_rank(name: &str, os: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) => (stem, Some(extension)),
+        None => (name.as_str(), None),
+    };
+    let build_extension = match os {
+        "windows" => Some("exe"),
+        _ => None,
+    };
+    if extension != build_extension || !stem.starts_with(BINARY_NAME) {
+        return None;
+    }
+
+    match stem == BINARY_NAME {
+        true => Some(2),
+        false if stem.contains(os) => Some(1),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_rate_limited, release_asset_rank};
+
+    #[test]
+    fn release_assets_are_matched_by_name() {
+        assert_eq!(
+            release_asset_rank("astro_modloader.exe", "windows"),
+            Some(2)
+        );
+        assert_eq!(release_asset_rank("astro_modloader", "windows"), None);
+        assert_eq!(release_asset_rank("astro_modloader", "linux"), Some(2));
+        assert_eq!(
+            release_asset_rank("astro_modloader-linux", "linux"),
+            Some(1)
+        );
+        assert_eq!(release_asset_rank("astro_modloader.exe", "linux"), None);
+        assert_eq!(release_asset_rank("astro_modloader.sha256", "linux"), None);
+        assert_eq!(release_asset_rank("astro_modloader-macos", "linux"), None);
+        assert_eq!(release_asset_rank("checksums", "linux"), None);
+    }
+
+    #[test]
+    fn rate_limits_are_detected_from_the_status() {
+        assert!(is_rate_limited(429, None));
+        assert!(is_rate_limited(403, Some("0")));
+        assert!(!is_rate_limited(403, Some("12")));
+        assert!(!is_rate_limited(403, None));
+        assert!(!is_rate_limited(404, Some("0")));
+    }
+}
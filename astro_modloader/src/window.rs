@@ -0,0 +1,132 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Once};
+use std::thread;
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+#[cfg(not(windows))]
+use log::debug;
+use log::warn;
+
+use crate::settings::SETTINGS;
+
+/// Title the window is created with, unreal_modloader needs it at compile time.
+/// Forks can brand the window by setting `ASTRO_MODLOADER_WINDOW_TITLE` at build time.
+pub const WINDOW_TITLE: &str = match option_env!("ASTRO_MODLOADER_WINDOW_TITLE") {
+    Some(title) => title,
+    None => concat!("Astroneer Modloader ", env!("CARGO_PKG_VERSION")),
+};
+
+/// How often to look for the window before giving up on retitling it
+const FIND_WINDOW_ATTEMPTS: u32 = 50;
+/// Wait between looking for the window
+const FIND_WINDOW_DELAY: Duration = Duration::from_millis(100);
+
+lazy_static! {
+    /// Title to set once the window exists
+    static ref PENDING_TITLE: Mutex<String> = Mutex::new(String::from(WINDOW_TITLE));
+}
+
+/// Guards the thread waiting for the window, so only one ever runs
+static RETITLE_THREAD: Once = Once::new();
+/// Set once the window was found, later titles are set right away
+static WINDOW_RETITLED: AtomicBool = AtomicBool::new(false);
+
+/// Window title from the title in the settings, or [`WINDOW_TITLE`], and the detected game build
+fn window_title(title: Option<&str>, game_build: Option<&str>) -> String {
+    let title = title.unwrap_or(WINDOW_TITLE);
+    match game_build {
+        Some(game_build) => format!("{} — build {}", title, game_build),
+        None => title.to_string(),
+    }
+}
+
+/// Retitle the window with the loader settings and the detected game build, once it exists.
+///
+/// The build is read for every detected install, the last one read is shown.
+pub fn show_game_build(game_build: Option<&str>) {
+    let title = window_title(SETTINGS.window_title.as_deref(), game_build);
+    if WINDOW_RETITLED.load(Ordering::SeqCst) {
+        set_window_title(&title);
+        return;
+    }
+    *PENDING_TITLE.lock().unwrap() = title;
+
+    // one thread waits for the window, whatever build was read last when it appears wins
+    RETITLE_THREAD.call_once(|| {
+        thread::spawn(|| {
+            for _ in 0..FIND_WINDOW_ATTEMPTS {
+                let title = PENDING_TITLE.lock().unwrap().clone();
+                if set_window_title(&title) {
+                    WINDOW_RETITLED.store(true, Ordering::SeqCst);
+                    return;
+                }
+                thread::sleep(FIND_WINDOW_DELAY);
+            }
+            warn!("Could not find the modloader window to set its title");
+        });
+    });
+}
+
+/// Set the title of this process's window, `false` if it has none yet
+#[cfg(windows)]
+fn set_window_title(title: &str) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+
+    use winapi::shared::minwindef::{BOOL, FALSE, LPARAM, TRUE};
+    use winapi::shared::windef::HWND;
+    use winapi::um::processthreadsapi::GetCurrentProcessId;
+    use winapi::um::winuser::{
+        EnumWindows, GetWindowThreadProcessId, IsWindowVisible, SetWindowTextW,
+    };
+
+    unsafe extern "system" fn find_own_window(hwnd: HWND, found: LPARAM) -> BOOL {
+        let mut process_id = 0;
+        GetWindowThreadProcessId(hwnd, &mut process_id);
+        if process_id == GetCurrentProcessId() && IsWindowVisible(hwnd) != 0 {
+            *(found as *mut HWND) = hwnd;
+            return FALSE;
+        }
+        TRUE
+    }
+
+    let mut window: HWND = ptr::null_mut();
+    unsafe {
+        EnumWindows(Some(find_own_window), &mut window as *mut HWND as LPARAM);
+    }
+    if window.is_null() {
+        return false;
+    }
+
+    let title: Vec<u16> = std::ffi::OsStr::new(title)
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+    unsafe { SetWindowTextW(window, title.as_ptr()) != 0 }
+}
+
+/// Retitling is only supported on Windows so far, elsewhere the window keeps [`WINDOW_TITLE`].
+/// Reports success so nothing waits for a window it could never retitle.
+#[cfg(not(windows))]
+fn set_window_title(title: &str) -> bool {
+    debug!(
+        "Window titles can only be changed on Windows, not showing {:?}",
+        title
+    );
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{window_title, WINDOW_TITLE};
+
+    #[test]
+    fn game_build_is_appended_to_the_title() {
+        assert_eq!(
+            window_title(Some("Fork Loader"), Some("1.28.103.0")),
+            "Fork Loader — build 1.28.103.0"
+        );
+        assert_eq!(window_title(None, None), WINDOW_TITLE);
+    }
+}